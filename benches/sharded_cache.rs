@@ -0,0 +1,82 @@
+// Allocation throughput with one shared cache shard versus one shard per
+// worker thread. Build without the `trace` feature, whose logging
+// serializes the threads on stdout.
+
+use atomalloc::{config::AtomAllocConfig, AtomAlloc};
+use smol::{channel, Executor};
+use std::{
+    alloc::Layout,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+const TASKS: usize = 64;
+const ROUNDS: usize = 200;
+
+fn run(threads: usize, cache_shards: usize) -> Duration {
+    let ex = Arc::new(Executor::new());
+    let (stop, stopped) = channel::unbounded::<()>();
+    let workers: Vec<_> = (0..threads)
+        .map(|_| {
+            let ex = ex.clone();
+            let stopped = stopped.clone();
+            thread::spawn(move || smol::block_on(ex.run(stopped.recv())))
+        })
+        .collect();
+
+    let config = AtomAllocConfig {
+        cache_shards,
+        ..AtomAllocConfig::default()
+    };
+    let elapsed = smol::block_on(async {
+        let alloc = Arc::new(AtomAlloc::with_config(config).await);
+        let layout = Layout::from_size_align(256, 8).unwrap();
+        let start = Instant::now();
+
+        let tasks: Vec<_> = (0..TASKS)
+            .map(|_| {
+                let alloc = alloc.clone();
+                ex.spawn(async move {
+                    for _ in 0..ROUNDS {
+                        let block = alloc.allocate(layout).await.unwrap();
+                        block.write_u64_le(0, 1).await.unwrap();
+                        alloc.deallocate(block).await.unwrap();
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await;
+        }
+
+        start.elapsed()
+    });
+
+    drop(stop);
+    for worker in workers {
+        let _ = worker.join();
+    }
+    elapsed
+}
+
+fn main() {
+    // Oversubscribe small hosts so sharding still has contention to relieve
+    let max_threads = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .max(4);
+    let ops = (TASKS * ROUNDS) as f64;
+
+    let mut threads = 1;
+    while threads <= max_threads {
+        let shared = run(threads, 1);
+        let sharded = run(threads, threads);
+        println!(
+            "{:>3} threads: shared {:>10.0} ops/s, sharded {:>10.0} ops/s",
+            threads,
+            ops / shared.as_secs_f64(),
+            ops / sharded.as_secs_f64()
+        );
+        threads *= 2;
+    }
+}
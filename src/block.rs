@@ -1,21 +1,229 @@
+use crate::cache::BlockCache;
+use crate::checksum::Crc32c;
+use crate::cow::CowBlock;
+use crate::cursor::{BlockReader, BlockWriter};
 use crate::error::BlockError;
+use crate::frozen::FrozenBlock;
+use crate::handle::BlockHandle;
+use crate::lease::{BlockReadLease, BlockWriteLease};
+use crate::slice::BlockSlice;
 use std::pin::Pin;
 use std::{
     future::Future,
+    ops::{Bound, Range, RangeBounds},
+    panic::Location,
     sync::{
         atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering},
-        Arc,
+        Arc, OnceLock, Weak,
     },
 };
 
-// Pack flags into a single atomic
+// Pack flags, lease state and generation into a single atomic
 const IN_USE_FLAG: u64 = 1 << 63;
 const ZEROED_FLAG: u64 = 1 << 62;
+const WRITE_LEASE_FLAG: u64 = 1 << 61;
+const READ_LEASE_SHIFT: u32 = 48;
+const READ_LEASE_UNIT: u64 = 1 << READ_LEASE_SHIFT;
+const READ_LEASE_MASK: u64 = 0x1fff << READ_LEASE_SHIFT;
+// Generations keep the low 48 bits below the lease count
+pub(crate) const GENERATION_MASK: u64 = READ_LEASE_UNIT - 1;
+
+// Checksum word: CRC32C in the low bits plus seal/verification flags
+const CHECKSUM_SEALED: u64 = 1 << 32;
+const CHECKSUM_VERIFY_ON_READ: u64 = 1 << 33;
+
+// Invokes `$m!` once per multi-byte numeric type with the names of its
+// little/big-endian read and write accessors
+macro_rules! for_each_number {
+    ($m:ident) => {
+        $m!(u16, read_u16_le, read_u16_be, write_u16_le, write_u16_be);
+        $m!(u32, read_u32_le, read_u32_be, write_u32_le, write_u32_be);
+        $m!(u64, read_u64_le, read_u64_be, write_u64_le, write_u64_be);
+        $m!(i16, read_i16_le, read_i16_be, write_i16_le, write_i16_be);
+        $m!(i32, read_i32_le, read_i32_be, write_i32_le, write_i32_be);
+        $m!(i64, read_i64_le, read_i64_be, write_i64_le, write_i64_be);
+        $m!(f32, read_f32_le, read_f32_be, write_f32_le, write_f32_be);
+        $m!(f64, read_f64_le, read_f64_be, write_f64_le, write_f64_be);
+    };
+}
+pub(crate) use for_each_number;
+
+macro_rules! number_readers {
+    ($ty:ty, $read_le:ident, $read_be:ident, $write_le:ident, $write_be:ident) => {
+        pub async fn $read_le(&self, offset: usize) -> Result<$ty, BlockError> {
+            Ok(<$ty>::from_le_bytes(self.read_array(offset).await?))
+        }
+
+        pub async fn $read_be(&self, offset: usize) -> Result<$ty, BlockError> {
+            Ok(<$ty>::from_be_bytes(self.read_array(offset).await?))
+        }
+    };
+}
+
+macro_rules! number_writers {
+    ($ty:ty, $read_le:ident, $read_be:ident, $write_le:ident, $write_be:ident) => {
+        pub async fn $write_le(&self, offset: usize, value: $ty) -> Result<(), BlockError> {
+            self.write(offset, &value.to_le_bytes()).await
+        }
+
+        pub async fn $write_be(&self, offset: usize, value: $ty) -> Result<(), BlockError> {
+            self.write(offset, &value.to_be_bytes()).await
+        }
+    };
+}
+
+// Typed accessors for any type exposing a bounds-checked `read`
+macro_rules! typed_readers {
+    () => {
+        pub async fn read_array<const N: usize>(
+            &self,
+            offset: usize,
+        ) -> Result<[u8; N], BlockError> {
+            let bytes = self.read(offset, N).await?;
+            let mut array = [0u8; N];
+            array.copy_from_slice(&bytes);
+            Ok(array)
+        }
+
+        pub async fn read_u8(&self, offset: usize) -> Result<u8, BlockError> {
+            Ok(u8::from_le_bytes(self.read_array(offset).await?))
+        }
+
+        pub async fn read_i8(&self, offset: usize) -> Result<i8, BlockError> {
+            Ok(i8::from_le_bytes(self.read_array(offset).await?))
+        }
+
+        $crate::block::for_each_number!(number_readers);
+    };
+}
+
+// Typed accessors for any type exposing a bounds-checked `write`
+macro_rules! typed_writers {
+    () => {
+        pub async fn write_u8(&self, offset: usize, value: u8) -> Result<(), BlockError> {
+            self.write(offset, &[value]).await
+        }
+
+        pub async fn write_i8(&self, offset: usize, value: i8) -> Result<(), BlockError> {
+            self.write(offset, &value.to_le_bytes()).await
+        }
+
+        $crate::block::for_each_number!(number_writers);
+    };
+}
+pub(crate) use {number_readers, number_writers, typed_readers, typed_writers};
+
+// Resolves `range` against a region of `size` bytes
+pub(crate) fn resolve_range(
+    range: impl RangeBounds<usize>,
+    size: usize,
+) -> Result<Range<usize>, BlockError> {
+    // Inclusive bounds at usize::MAX have no exclusive form, and are out
+    // of bounds anyway
+    let start = match range.start_bound() {
+        Bound::Included(&start) => Some(start),
+        Bound::Excluded(&start) => start.checked_add(1),
+        Bound::Unbounded => Some(0),
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.checked_add(1),
+        Bound::Excluded(&end) => Some(end),
+        Bound::Unbounded => Some(size),
+    };
+    let (Some(start), Some(end)) = (start, end) else {
+        return Err(BlockError::OutOfBounds {
+            offset: start.unwrap_or(usize::MAX),
+            len: 0,
+            size,
+        });
+    };
+
+    if start > end || end > size {
+        return Err(BlockError::OutOfBounds {
+            offset: start,
+            len: end.saturating_sub(start),
+            size,
+        });
+    }
+
+    Ok(start..end)
+}
 
 pub struct Block {
-    state: AtomicU64, // generation + flags
+    state: AtomicU64,   // generation + flags
+    version: AtomicU64, // seqlock counter, odd while a write is in progress
     size: AtomicUsize,
     data: Box<[AtomicU8]>,
+    checksum: AtomicU64,              // sealed CRC32C + flags
+    allocation_id: AtomicU64,         // leak tracking id, 0 if untracked
+    owner: AtomicU64,                 // id of the allocator that created it
+    home: OnceLock<Weak<BlockCache>>, // cache the block is returned to
+}
+
+// Holds the block's version odd for the duration of a write. Dropping the
+// guard publishes the write, even if the writing future is cancelled.
+struct WriteSection<'a> {
+    block: &'a Block,
+}
+
+impl WriteSection<'_> {
+    // Any write invalidates a sealed checksum
+    async fn enter(block: &Block) -> WriteSection<'_> {
+        let section = WriteSection::enter_keeping_seal(block).await;
+        block.unseal();
+        section
+    }
+
+    // For the allocator's scrubbing of freed blocks, which a seal should
+    // not survive unnoticed
+    async fn enter_keeping_seal(block: &Block) -> WriteSection<'_> {
+        loop {
+            let version = block.version.load(Ordering::Acquire);
+            if version & 1 == 0
+                && block
+                    .version
+                    .compare_exchange_weak(
+                        version,
+                        version + 1,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                    )
+                    .is_ok()
+            {
+                return WriteSection { block };
+            }
+            smol::future::yield_now().await;
+        }
+    }
+
+    // Enters only if the block is still at `expected`, i.e. nobody has
+    // written since the caller observed that version
+    fn try_enter(block: &Block, expected: u64) -> Result<WriteSection<'_>, BlockError> {
+        if expected & 1 == 1 {
+            return Err(BlockError::VersionConflict {
+                current: block.version(),
+            });
+        }
+
+        match block.version.compare_exchange(
+            expected,
+            expected + 1,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => {
+                block.unseal();
+                Ok(WriteSection { block })
+            }
+            Err(current) => Err(BlockError::VersionConflict { current }),
+        }
+    }
+}
+
+impl Drop for WriteSection<'_> {
+    fn drop(&mut self) {
+        self.block.version.fetch_add(1, Ordering::Release);
+    }
 }
 
 pub trait BlockOps: Send + Sync {
@@ -28,7 +236,8 @@ pub trait BlockOps: Send + Sync {
 
 impl Block {
     pub fn new(size: usize, generation: u64) -> Pin<Arc<Self>> {
-        let state = AtomicU64::new(generation);
+        // Fresh storage starts out zeroed
+        let state = AtomicU64::new((generation & GENERATION_MASK) | ZEROED_FLAG);
         let size_atomic = AtomicUsize::new(size);
         let data = (0..size)
             .map(|_| AtomicU8::new(0))
@@ -37,12 +246,84 @@ impl Block {
 
         Pin::new(Arc::new(Self {
             state,
+            version: AtomicU64::new(0),
             size: size_atomic,
             data,
+            checksum: AtomicU64::new(0),
+            allocation_id: AtomicU64::new(0),
+            owner: AtomicU64::new(0),
+            home: OnceLock::new(),
         }))
     }
 
+    // Holds the write lease for the duration, so read lease holders never
+    // see the write. A task holding a lease must write through it instead.
     pub async fn write(&self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        let _lease = self.lease_write().await;
+        self.write_leased(offset, data).await
+    }
+
+    // `write` for callers that already hold the write lease
+    pub(crate) async fn write_leased(&self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        self.check_in_use()?;
+        let size = self.size.load(Ordering::Acquire);
+        if offset + data.len() > size {
+            return Err(BlockError::OutOfBounds {
+                offset,
+                len: data.len(),
+                size,
+            });
+        }
+
+        let _section = WriteSection::enter(self).await;
+        self.store_bytes(offset, data).await;
+        Ok(())
+    }
+
+    // Writes only if the block is still at `generation` once the write
+    // section is held, so a block reissued while the writer waited for the
+    // section is left alone
+    pub(crate) async fn write_checked(
+        &self,
+        generation: u64,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), BlockError> {
+        self.check_in_use()?;
+        let size = self.size.load(Ordering::Acquire);
+        if offset + data.len() > size {
+            return Err(BlockError::OutOfBounds {
+                offset,
+                len: data.len(),
+                size,
+            });
+        }
+
+        let _lease = self.lease_write().await;
+        let _section = WriteSection::enter(self).await;
+        let current = self.generation();
+        if current != generation {
+            return Err(BlockError::InvalidGeneration {
+                block: current,
+                expected: generation,
+            });
+        }
+        self.check_in_use()?;
+        self.store_bytes(offset, data).await;
+        Ok(())
+    }
+
+    // Commits the write only if no other write has happened since the
+    // caller observed `expected_version` (see `read_versioned`). Takes the
+    // write lease, so it never returns in a task holding a lease on this
+    // block.
+    pub async fn write_if_version(
+        &self,
+        expected_version: u64,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), BlockError> {
+        self.check_in_use()?;
         let size = self.size.load(Ordering::Acquire);
         if offset + data.len() > size {
             return Err(BlockError::OutOfBounds {
@@ -52,6 +333,13 @@ impl Block {
             });
         }
 
+        let _lease = self.lease_write().await;
+        let _section = WriteSection::try_enter(self, expected_version)?;
+        self.store_bytes(offset, data).await;
+        Ok(())
+    }
+
+    async fn store_bytes(&self, offset: usize, data: &[u8]) {
         // Process in chunks for cache efficiency
         const CHUNK_SIZE: usize = 1024;
         for chunk_start in (0..data.len()).step_by(CHUNK_SIZE) {
@@ -63,15 +351,150 @@ impl Block {
         }
 
         self.state.fetch_and(!ZEROED_FLAG, Ordering::Release);
-        Ok(())
     }
 
     pub async fn read(&self, offset: usize, len: usize) -> Result<Vec<u8>, BlockError> {
+        self.check_in_use()?;
+        let size = self.size.load(Ordering::Acquire);
+        if offset + len > size {
+            return Err(BlockError::OutOfBounds { offset, len, size });
+        }
+
+        self.verify_on_read().await?;
+        Ok(self.load_bytes(offset, len).await)
+    }
+
+    // Like `read`, but retries until no write overlapped the read, so the
+    // result is always the product of complete `write` calls
+    pub async fn read_consistent(&self, offset: usize, len: usize) -> Result<Vec<u8>, BlockError> {
+        let (result, _) = self.read_versioned(offset, len).await?;
+        Ok(result)
+    }
+
+    // Consistent read that also returns the version it observed, for use
+    // with `write_if_version`
+    pub async fn read_versioned(
+        &self,
+        offset: usize,
+        len: usize,
+    ) -> Result<(Vec<u8>, u64), BlockError> {
+        self.check_in_use()?;
         let size = self.size.load(Ordering::Acquire);
         if offset + len > size {
             return Err(BlockError::OutOfBounds { offset, len, size });
         }
 
+        self.verify_on_read().await?;
+        loop {
+            let before = self.version.load(Ordering::Acquire);
+            if before & 1 == 0 {
+                let result = self.load_bytes(offset, len).await;
+                if self.version.load(Ordering::Acquire) == before {
+                    return Ok((result, before));
+                }
+            }
+            smol::future::yield_now().await;
+        }
+    }
+
+    // Computes and stores a checksum of the whole block. Any later write
+    // drops the seal until the block is sealed again.
+    pub async fn seal(&self) -> u32 {
+        loop {
+            let (checksum, version) = self.compute_checksum().await;
+            let _ = self
+                .checksum
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                    Some((current & CHECKSUM_VERIFY_ON_READ) | CHECKSUM_SEALED | checksum as u64)
+                });
+
+            if self.version() == version {
+                return checksum;
+            }
+
+            // A write raced the seal, so the stored checksum may be stale
+            self.checksum.fetch_and(!CHECKSUM_SEALED, Ordering::AcqRel);
+        }
+    }
+
+    pub(crate) fn unseal(&self) {
+        self.checksum.fetch_and(!CHECKSUM_SEALED, Ordering::AcqRel);
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        self.checksum.load(Ordering::Acquire) & CHECKSUM_SEALED != 0
+    }
+
+    // Recomputes the checksum of a sealed block; unsealed blocks pass.
+    // Writes through the block drop the seal, so a mismatch means the
+    // allocator has scrubbed the block since: it was freed after sealing.
+    pub async fn verify(&self) -> Result<(), BlockError> {
+        if !self.is_sealed() {
+            return Ok(());
+        }
+
+        let (actual, _) = self.compute_checksum().await;
+        let stored = self.checksum.load(Ordering::Acquire);
+        if stored & CHECKSUM_SEALED == 0 {
+            // Unsealed by a write while we were hashing
+            return Ok(());
+        }
+
+        let expected = stored as u32;
+        if actual != expected {
+            return Err(BlockError::ChecksumMismatch { expected, actual });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_verify_on_read(&self, enabled: bool) {
+        if enabled {
+            self.checksum
+                .fetch_or(CHECKSUM_VERIFY_ON_READ, Ordering::Release);
+        } else {
+            self.checksum
+                .fetch_and(!CHECKSUM_VERIFY_ON_READ, Ordering::Release);
+        }
+    }
+
+    pub(crate) async fn verify_on_read(&self) -> Result<(), BlockError> {
+        if self.checksum.load(Ordering::Acquire) & CHECKSUM_VERIFY_ON_READ != 0 {
+            self.verify().await?;
+        }
+        Ok(())
+    }
+
+    // CRC32C over the whole block, with the version it was stable at
+    async fn compute_checksum(&self) -> (u32, u64) {
+        const CHUNK_SIZE: usize = 1024;
+        let size = self.size();
+
+        loop {
+            let before = self.version.load(Ordering::Acquire);
+            if before & 1 == 0 {
+                let mut crc = Crc32c::new();
+                for offset in (0..size).step_by(CHUNK_SIZE) {
+                    let end = (offset + CHUNK_SIZE).min(size);
+                    for i in offset..end {
+                        crc.update(self.data[i].load(Ordering::Acquire));
+                    }
+                    smol::future::yield_now().await;
+                }
+
+                if self.version.load(Ordering::Acquire) == before {
+                    return (crc.finish(), before);
+                }
+            }
+            smol::future::yield_now().await;
+        }
+    }
+
+    // Advances by two per write or clear; odd while one is in progress
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    pub(crate) async fn load_bytes(&self, offset: usize, len: usize) -> Vec<u8> {
         let mut result = Vec::with_capacity(len);
 
         // Read in chunks for cache efficiency
@@ -84,29 +507,344 @@ impl Block {
             smol::future::yield_now().await;
         }
 
-        Ok(result)
+        result
     }
 
-    pub fn update_generation(&self, new_gen: u64) {
-        let current = self.state.load(Ordering::Acquire);
-        let flags = current & (IN_USE_FLAG | ZEROED_FLAG);
-        let new_state = new_gen | flags;
-        self.state.store(new_state, Ordering::Release);
+    typed_readers!();
+    typed_writers!();
+
+    // Takes the write lease like `write`; a task holding a lease must fill
+    // through it instead
+    pub async fn fill(&self, range: impl RangeBounds<usize>, byte: u8) -> Result<(), BlockError> {
+        let _lease = self.lease_write().await;
+        self.fill_leased(range, byte).await
     }
 
-    pub async fn clear(&self) {
-        // Clear in chunks for async friendliness
+    // `fill` for callers that already hold the write lease
+    pub(crate) async fn fill_leased(
+        &self,
+        range: impl RangeBounds<usize>,
+        byte: u8,
+    ) -> Result<(), BlockError> {
+        self.check_in_use()?;
+        let range = resolve_range(range, self.size())?;
+        self.store_fill(range, byte).await;
+        Ok(())
+    }
+
+    // Fills without the in-use check
+    async fn store_fill(&self, range: Range<usize>, byte: u8) {
+        let _section = WriteSection::enter(self).await;
+        self.store_range(range, byte).await;
+        self.state.fetch_and(!ZEROED_FLAG, Ordering::Release);
+    }
+
+    // Poisons or zeroes a freed block for the allocator. A seal is left in
+    // place, so verifying through a reference kept past `deallocate`
+    // reports the change instead of passing on the scrubbed contents.
+    pub(crate) async fn scrub(&self, byte: u8) {
+        let _section = WriteSection::enter_keeping_seal(self).await;
+        self.store_range(0..self.size(), byte).await;
+        if byte == 0 {
+            self.state.fetch_or(ZEROED_FLAG, Ordering::Release);
+        } else {
+            self.state.fetch_and(!ZEROED_FLAG, Ordering::Release);
+        }
+    }
+
+    // Caller holds a write section
+    async fn store_range(&self, range: Range<usize>, byte: u8) {
+        // Process in chunks for async friendliness
+        const CHUNK_SIZE: usize = 1024;
+        for chunk_start in range.clone().step_by(CHUNK_SIZE) {
+            let chunk_end = (chunk_start + CHUNK_SIZE).min(range.end);
+            for i in chunk_start..chunk_end {
+                self.data[i].store(byte, Ordering::Release);
+            }
+            smol::future::yield_now().await;
+        }
+    }
+
+    // Compares `len` bytes at `offset` with `len` bytes of `other` at
+    // `other_offset` without copying either side out
+    pub async fn eq_range(
+        &self,
+        offset: usize,
+        other: &Block,
+        other_offset: usize,
+        len: usize,
+    ) -> Result<bool, BlockError> {
+        for (block, offset) in [(self, offset), (other, other_offset)] {
+            block.check_in_use()?;
+            let size = block.size();
+            if offset + len > size {
+                return Err(BlockError::OutOfBounds { offset, len, size });
+            }
+        }
+
+        const CHUNK_SIZE: usize = 1024;
+        for chunk_start in (0..len).step_by(CHUNK_SIZE) {
+            let chunk_end = (chunk_start + CHUNK_SIZE).min(len);
+            for i in chunk_start..chunk_end {
+                let a = self.data[offset + i].load(Ordering::Acquire);
+                let b = other.data[other_offset + i].load(Ordering::Acquire);
+                if a != b {
+                    return Ok(false);
+                }
+            }
+            smol::future::yield_now().await;
+        }
+
+        Ok(true)
+    }
+
+    // Offset of the first occurrence of `pattern`, if any
+    pub async fn find(&self, pattern: &[u8]) -> Result<Option<usize>, BlockError> {
+        self.check_in_use()?;
+        let size = self.size();
+        if pattern.len() > size {
+            return Ok(None);
+        }
+
+        const CHUNK_SIZE: usize = 1024;
+        let last_start = size - pattern.len();
+        for chunk_start in (0..=last_start).step_by(CHUNK_SIZE) {
+            let chunk_end = (chunk_start + CHUNK_SIZE).min(last_start + 1);
+            for start in chunk_start..chunk_end {
+                if self.matches_at(start, pattern) {
+                    return Ok(Some(start));
+                }
+            }
+            smol::future::yield_now().await;
+        }
+
+        Ok(None)
+    }
+
+    pub async fn starts_with(&self, prefix: &[u8]) -> Result<bool, BlockError> {
+        self.check_in_use()?;
+        if prefix.len() > self.size() {
+            return Ok(false);
+        }
+
+        const CHUNK_SIZE: usize = 1024;
+        for chunk_start in (0..prefix.len()).step_by(CHUNK_SIZE) {
+            let chunk_end = (chunk_start + CHUNK_SIZE).min(prefix.len());
+            if !self.matches_at(chunk_start, &prefix[chunk_start..chunk_end]) {
+                return Ok(false);
+            }
+            smol::future::yield_now().await;
+        }
+
+        Ok(true)
+    }
+
+    // Offset of the first byte that differs from `byte`, if any
+    pub(crate) async fn find_mismatch(&self, byte: u8) -> Option<usize> {
         const CHUNK_SIZE: usize = 1024;
         let size = self.size();
 
         for offset in (0..size).step_by(CHUNK_SIZE) {
             let end = (offset + CHUNK_SIZE).min(size);
             for i in offset..end {
-                self.data[i].store(0, Ordering::Release);
+                if self.data[i].load(Ordering::Acquire) != byte {
+                    return Some(i);
+                }
+            }
+            smol::future::yield_now().await;
+        }
+
+        None
+    }
+
+    pub fn is_zeroed(&self) -> bool {
+        self.state.load(Ordering::Acquire) & ZEROED_FLAG != 0
+    }
+
+    fn matches_at(&self, offset: usize, pattern: &[u8]) -> bool {
+        pattern
+            .iter()
+            .enumerate()
+            .all(|(i, &byte)| self.data[offset + i].load(Ordering::Acquire) == byte)
+    }
+
+    // Zero-based view over `range` that keeps this block alive
+    pub fn slice(
+        self: &Pin<Arc<Self>>,
+        range: impl RangeBounds<usize>,
+    ) -> Result<BlockSlice, BlockError> {
+        BlockSlice::new(self.clone(), range)
+    }
+
+    // Read-only, cheaply cloneable form of this block. The block goes back
+    // to its size class once the last clone is dropped. Only a block with
+    // no other clones, slices, handles or weak references can be frozen;
+    // otherwise it is given back unchanged.
+    pub fn freeze(self: Pin<Arc<Self>>) -> Result<FrozenBlock, Pin<Arc<Self>>> {
+        let mut block = Pin::into_inner(self);
+        // A weak reference could be upgraded into a writable clone later
+        if Arc::get_mut(&mut block).is_none() {
+            return Err(Pin::new(block));
+        }
+        Ok(FrozenBlock::new(Pin::new(block)))
+    }
+
+    // Handle pinned to the block's current generation; it stops working
+    // once the block is freed and handed out again
+    pub fn handle(self: &Pin<Arc<Self>>) -> BlockHandle {
+        BlockHandle::new(self.clone())
+    }
+
+    // Handle that shares this block's storage until its first write. The
+    // private copy is tracked as allocated here.
+    #[track_caller]
+    pub fn cow_clone(self: &Pin<Arc<Self>>) -> CowBlock {
+        CowBlock::new(self.clone(), Location::caller())
+    }
+
+    pub(crate) fn set_home(&self, cache: Weak<BlockCache>) {
+        let _ = self.home.set(cache);
+    }
+
+    pub(crate) fn set_owner(&self, allocator: u64) {
+        self.owner.store(allocator, Ordering::Release);
+    }
+
+    pub(crate) fn owner(&self) -> u64 {
+        self.owner.load(Ordering::Acquire)
+    }
+
+    pub fn is_in_use(&self) -> bool {
+        self.state.load(Ordering::Acquire) & IN_USE_FLAG != 0
+    }
+
+    // Blocks sitting in a free queue belong to nobody and reject access
+    fn check_in_use(&self) -> Result<(), BlockError> {
+        if !self.is_in_use() {
+            return Err(BlockError::NotInitialized);
+        }
+        Ok(())
+    }
+
+    // Whether the block is still allocated under `generation`
+    pub(crate) fn check_live(&self, generation: u64) -> Result<(), BlockError> {
+        self.check_in_use()?;
+        let current = self.generation();
+        if current != generation {
+            return Err(BlockError::InvalidGeneration {
+                block: current,
+                expected: generation,
+            });
+        }
+        Ok(())
+    }
+
+    // Clears IN_USE_FLAG, reporting whether it was set
+    pub(crate) fn try_release(&self) -> bool {
+        let current = self.state.fetch_and(!IN_USE_FLAG, Ordering::AcqRel);
+        current & IN_USE_FLAG != 0
+    }
+
+    pub(crate) fn set_allocation_id(&self, id: u64) {
+        self.allocation_id.store(id, Ordering::Release);
+    }
+
+    pub(crate) fn take_allocation_id(&self) -> Option<u64> {
+        match self.allocation_id.swap(0, Ordering::AcqRel) {
+            0 => None,
+            id => Some(id),
+        }
+    }
+
+    pub(crate) fn home(&self) -> Option<Arc<BlockCache>> {
+        self.home.get().and_then(Weak::upgrade)
+    }
+
+    // Cursors over the block contents, starting at offset 0
+    pub fn reader(&self) -> BlockReader<'_> {
+        BlockReader::new(self)
+    }
+
+    pub fn writer(&self) -> BlockWriter<'_> {
+        BlockWriter::new(self)
+    }
+
+    pub fn update_generation(&self, new_gen: u64) {
+        // Leases may change concurrently, so only swap the generation bits
+        let _ = self
+            .state
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                Some((current & !GENERATION_MASK) | (new_gen & GENERATION_MASK))
+            });
+    }
+
+    // Exclusive lease: granted only when no reader or writer holds one
+    pub fn try_lease_write(&self) -> Option<BlockWriteLease<'_>> {
+        self.state
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                if current & (WRITE_LEASE_FLAG | READ_LEASE_MASK) == 0 {
+                    Some(current | WRITE_LEASE_FLAG)
+                } else {
+                    None
+                }
+            })
+            .ok()
+            .map(|_| BlockWriteLease::new(self))
+    }
+
+    pub async fn lease_write(&self) -> BlockWriteLease<'_> {
+        loop {
+            if let Some(lease) = self.try_lease_write() {
+                return lease;
+            }
+            smol::future::yield_now().await;
+        }
+    }
+
+    // Shared lease: granted while no writer holds a lease
+    pub fn try_lease_read(&self) -> Option<BlockReadLease<'_>> {
+        self.state
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                let readers = current & READ_LEASE_MASK;
+                if current & WRITE_LEASE_FLAG == 0 && readers != READ_LEASE_MASK {
+                    Some(current + READ_LEASE_UNIT)
+                } else {
+                    None
+                }
+            })
+            .ok()
+            .map(|_| BlockReadLease::new(self))
+    }
+
+    pub async fn lease_read(&self) -> BlockReadLease<'_> {
+        loop {
+            if let Some(lease) = self.try_lease_read() {
+                return lease;
             }
             smol::future::yield_now().await;
         }
+    }
 
+    pub(crate) fn release_write_lease(&self) {
+        self.state.fetch_and(!WRITE_LEASE_FLAG, Ordering::Release);
+    }
+
+    pub(crate) fn release_read_lease(&self) {
+        self.state.fetch_sub(READ_LEASE_UNIT, Ordering::Release);
+    }
+
+    // Takes the write lease like `write`; a task holding a lease must clear
+    // through it instead
+    pub async fn clear(&self) {
+        let _lease = self.lease_write().await;
+        self.store_zero().await;
+    }
+
+    // Zeroes without the lease, for lease holders and for blocks being
+    // handed out again
+    pub(crate) async fn store_zero(&self) {
+        let _section = WriteSection::enter(self).await;
+        self.store_range(0..self.size(), 0).await;
         self.state.fetch_or(ZEROED_FLAG, Ordering::Release);
     }
 }
@@ -126,26 +864,13 @@ impl BlockOps for Block {
     }
 
     fn generation(&self) -> u64 {
-        self.state.load(Ordering::Acquire) & !(IN_USE_FLAG | ZEROED_FLAG)
+        self.state.load(Ordering::Acquire) & GENERATION_MASK
     }
 
     fn clear(self: Pin<&Arc<Self>>) -> impl Future<Output = ()> + Send + 'static {
         // Clone the Arc for the async block
         let block = Arc::clone(self.get_ref());
 
-        async move {
-            const CHUNK_SIZE: usize = 1024;
-            let size = block.size();
-
-            for offset in (0..size).step_by(CHUNK_SIZE) {
-                let end = (offset + CHUNK_SIZE).min(size);
-                for i in offset..end {
-                    block.data[i].store(0, Ordering::Release);
-                }
-                smol::future::yield_now().await;
-            }
-
-            block.state.fetch_or(ZEROED_FLAG, Ordering::Release);
-        }
+        async move { Block::clear(&block).await }
     }
 }
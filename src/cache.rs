@@ -1,75 +1,289 @@
-use crate::error::AtomAllocError;
+use crate::config::ZeroPolicy;
+use crate::error::{AtomAllocError, BlockError};
 use crate::manager::BlockManager;
+use crate::policy::{CacheEnd, CachePolicy, CacheView};
 use crate::pool::MemoryPool;
+use crate::tracker::{AllocationInfo, AllocationTracker};
 use crate::{
     block::{Block, BlockOps},
     stats::AtomAllocStats,
 };
 use crossbeam::queue::SegQueue;
+use crossbeam_skiplist::SkipMap;
+use smol::channel::Sender;
 use std::{
+    panic::Location,
     pin::Pin,
     sync::{
-        atomic::{AtomicUsize, Ordering},
-        Arc,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Weak,
     },
+    time::{Duration, Instant},
 };
 
+// A free block and when it was returned
+struct CachedBlock {
+    block: Pin<Arc<Block>>,
+    returned_at: Instant,
+}
+
+// Threads are numbered as they first touch a cache, which spreads them
+// evenly over the shards
+static NEXT_THREAD_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static THREAD_INDEX: usize = NEXT_THREAD_INDEX.fetch_add(1, Ordering::Relaxed);
+}
+
+// Free blocks of one size, kept in return order so the cache policy can
+// take from either end and evict from the old one. Blocks are spread over
+// per-thread shards to keep threads off each other's queues; a thread
+// whose shard is empty steals from the others.
 pub struct SizeClass {
     size: usize,
-    hot_queue: Arc<SegQueue<Pin<Arc<Block>>>>,
-    cold_queue: Arc<SegQueue<Pin<Arc<Block>>>>,
+    capacity: Option<usize>, // most blocks the class may hold
+    shards: Box<[SkipMap<u64, CachedBlock>]>,
+    next_seq: AtomicU64,   // keys for returned blocks count up from 2^63
+    oldest_seq: AtomicU64, // keys for reinserted blocks count down from it
+    len: AtomicUsize,
     allocation_count: AtomicUsize,
+    created: Instant,
+    last_get: AtomicU64,      // nanos since `created`, 0 = never
+    mean_interval: AtomicU64, // smoothed nanos between gets, 0 = unknown
 }
 
 impl SizeClass {
-    pub fn new(size: usize) -> Self {
+    pub fn new(size: usize, max_blocks: usize, max_bytes: usize, shards: usize) -> Self {
+        let by_count = (max_blocks > 0).then_some(max_blocks);
+        let by_bytes = (max_bytes > 0).then_some(max_bytes / size);
         Self {
             size,
-            hot_queue: Arc::new(SegQueue::new()),
-            cold_queue: Arc::new(SegQueue::new()),
+            capacity: by_count.into_iter().chain(by_bytes).min(),
+            shards: (0..shards.max(1)).map(|_| SkipMap::new()).collect(),
+            next_seq: AtomicU64::new(1 << 63),
+            oldest_seq: AtomicU64::new(1 << 63),
+            len: AtomicUsize::new(0),
             allocation_count: AtomicUsize::new(0),
+            created: Instant::now(),
+            last_get: AtomicU64::new(0),
+            mean_interval: AtomicU64::new(0),
         }
     }
 
-    pub async fn get_block(&self) -> Option<Pin<Arc<Block>>> {
-        // Check hot queue with retry
-        for _ in 0..2 {
-            if let Some(block) = self.hot_queue.pop() {
-                if block.size() == self.size && block.try_acquire() {
-                    self.allocation_count.fetch_add(1, Ordering::Relaxed);
-                    return Some(block);
-                }
-            }
+    pub fn view(&self) -> CacheView {
+        let mean_interval = self.mean_interval.load(Ordering::Relaxed);
+        CacheView {
+            block_size: self.size,
+            cached: self.len.load(Ordering::Relaxed),
+            allocations: self.allocation_count.load(Ordering::Relaxed),
+            mean_interval: (mean_interval > 0).then(|| Duration::from_nanos(mean_interval)),
         }
+    }
 
-        // Try cold queue once
-        if let Some(block) = self.cold_queue.pop() {
-            if block.size() == self.size && block.try_acquire() {
-                self.allocation_count.fetch_add(1, Ordering::Relaxed);
-                return Some(block);
-            }
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    // Takes a block from the given end of the own shard, stealing from the
+    // others if it is empty. Also reports whether the block was stolen.
+    pub fn pop(&self, end: CacheEnd) -> Option<(Pin<Arc<Block>>, bool)> {
+        let local = self.local_shard();
+        let shards = self.shards.len();
+        let (i, entry) = (0..shards).find_map(|i| {
+            let shard = &self.shards[(local + i) % shards];
+            let entry = match end {
+                CacheEnd::Newest => shard.pop_back(),
+                CacheEnd::Oldest => shard.pop_front(),
+            };
+            entry.map(|entry| (i, entry))
+        })?;
+
+        self.len.fetch_sub(1, Ordering::Relaxed);
+        Some((entry.value().block.clone(), i > 0))
+    }
+
+    // Puts back a popped block that could not be used right now, at the
+    // end `end` does not pop from, so it is looked at again last
+    pub fn reinsert(&self, block: Pin<Arc<Block>>, end: CacheEnd) {
+        let seq = match end {
+            CacheEnd::Newest => self.oldest_seq.fetch_sub(1, Ordering::Relaxed) - 1,
+            CacheEnd::Oldest => self.next_seq.fetch_add(1, Ordering::Relaxed),
+        };
+        self.shards[self.local_shard()].insert(
+            seq,
+            CachedBlock {
+                block,
+                returned_at: Instant::now(),
+            },
+        );
+        self.len.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_acquired(&self) {
+        self.allocation_count.fetch_add(1, Ordering::Relaxed);
+        self.record_get();
+    }
+
+    fn record_get(&self) {
+        let now = (self.created.elapsed().as_nanos() as u64).max(1);
+        let last = self.last_get.swap(now, Ordering::Relaxed);
+        if last == 0 {
+            return;
         }
 
-        None
+        let interval = now.saturating_sub(last);
+        let _ = self
+            .mean_interval
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |mean| {
+                Some(if mean == 0 {
+                    interval
+                } else {
+                    (mean * 7 + interval) / 8
+                })
+            });
     }
 
-    pub async fn return_block(&self, block: Pin<Arc<Block>>) {
-        let alloc_count = self.allocation_count.load(Ordering::Relaxed);
+    fn is_full(&self) -> bool {
+        self.capacity
+            .is_some_and(|capacity| self.len.load(Ordering::Relaxed) >= capacity)
+    }
 
+    // Returns false if the class is full or the policy declined the block
+    pub async fn return_block(&self, block: Pin<Arc<Block>>, policy: &dyn CachePolicy) -> bool {
         // Ensure the block size matches the size class
         if block.size() != self.size {
             panic!("Block size does not match size class");
         }
 
-        // Adaptive promotion based on allocation frequency
-        if alloc_count & 7 == 0 {
-            // Power of 2 mask
-            self.hot_queue.push(block);
-            println!("Returned block of size {} to hot queue", self.size);
-        } else {
-            self.cold_queue.push(block);
-            println!("Returned block of size {} to cold queue", self.size);
+        if self.is_full() || !policy.on_return(&self.view()) {
+            return false;
         }
+
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        self.shards[self.local_shard()].insert(
+            seq,
+            CachedBlock {
+                block,
+                returned_at: Instant::now(),
+            },
+        );
+        self.len.fetch_add(1, Ordering::Relaxed);
+        trace!("Returned block of size {} to size class", self.size);
+        true
+    }
+
+    // Takes idle blocks off the old end for as long as the policy wants
+    pub fn evict(&self, policy: &dyn CachePolicy) -> Vec<Pin<Arc<Block>>> {
+        let mut evicted = Vec::new();
+        for shard in self.shards.iter() {
+            for entry in shard.iter() {
+                // Blocks held elsewhere stay put until they are let go
+                if entry.value().block.is_in_use() {
+                    continue;
+                }
+                let idle = entry.value().returned_at.elapsed();
+                if !policy.should_evict(&self.view(), idle) {
+                    break;
+                }
+                // Another thread may have taken it in the meantime
+                if entry.remove() {
+                    self.len.fetch_sub(1, Ordering::Relaxed);
+                    evicted.push(entry.value().block.clone());
+                }
+            }
+        }
+        evicted
+    }
+
+    // Drops every cached block and forgets the class's demand history
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.clear();
+        }
+        self.len.store(0, Ordering::Relaxed);
+        self.allocation_count.store(0, Ordering::Relaxed);
+        self.last_get.store(0, Ordering::Relaxed);
+        self.mean_interval.store(0, Ordering::Relaxed);
+    }
+
+    fn local_shard(&self) -> usize {
+        THREAD_INDEX.with(|index| index % self.shards.len())
+    }
+}
+
+// Freed blocks wait here, oldest first, before rejoining their size class,
+// so a stale handle touching them hits a block nobody owns yet
+struct Quarantine {
+    queue: SegQueue<Pin<Arc<Block>>>,
+    blocks: AtomicUsize,
+    bytes: AtomicUsize,
+    max_blocks: usize,
+    max_bytes: usize,
+}
+
+impl Quarantine {
+    fn new(max_blocks: usize, max_bytes: usize) -> Self {
+        Self {
+            queue: SegQueue::new(),
+            blocks: AtomicUsize::new(0),
+            bytes: AtomicUsize::new(0),
+            max_blocks,
+            max_bytes,
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.max_blocks > 0 || self.max_bytes > 0
+    }
+
+    fn is_over_limit(&self) -> bool {
+        (self.max_blocks > 0 && self.blocks.load(Ordering::Acquire) > self.max_blocks)
+            || (self.max_bytes > 0 && self.bytes.load(Ordering::Acquire) > self.max_bytes)
+    }
+
+    fn push(&self, block: Pin<Arc<Block>>) {
+        self.blocks.fetch_add(1, Ordering::AcqRel);
+        self.bytes.fetch_add(block.size(), Ordering::AcqRel);
+        self.queue.push(block);
+    }
+
+    fn pop(&self) -> Option<Pin<Arc<Block>>> {
+        let block = self.queue.pop()?;
+        self.blocks.fetch_sub(1, Ordering::AcqRel);
+        self.bytes.fetch_sub(block.size(), Ordering::AcqRel);
+        Some(block)
+    }
+
+    fn clear(&self) {
+        while self.pop().is_some() {}
+    }
+}
+
+// Zeroes freed blocks off the deallocation path for
+// `ZeroPolicy::Background`, handing each back to the cache once clean
+struct ZeroWorker {
+    sender: Sender<Pin<Arc<Block>>>,
+}
+
+impl ZeroWorker {
+    fn spawn(cache: Weak<BlockCache>) -> Self {
+        let (sender, receiver) = smol::channel::unbounded::<Pin<Arc<Block>>>();
+        smol::spawn(async move {
+            // Ends once the cache, and with it the sender, is dropped
+            while let Ok(block) = receiver.recv().await {
+                block.scrub(0).await;
+                let Some(cache) = cache.upgrade() else {
+                    break;
+                };
+                cache.finish_zeroing(block).await;
+            }
+        })
+        .detach();
+        Self { sender }
     }
 }
 
@@ -77,33 +291,62 @@ pub struct BlockCache {
     manager: Arc<BlockManager>,
     pool: Arc<MemoryPool>,
     size_classes: Vec<Arc<SizeClass>>,
+    quarantine: Quarantine,
+    tracker: AllocationTracker,
+    policy: Arc<dyn CachePolicy>,
+    depot: Vec<SegQueue<Vec<Pin<Arc<Block>>>>>, // magazines per size class
+    zero_worker: Option<ZeroWorker>,
     stats: Arc<AtomAllocStats>,
 }
 
 impl BlockCache {
     // Power of 2 size classes for better alignment and less fragmentation
-    const SIZE_CLASSES: &'static [usize] = &[32, 64, 128, 256, 512, 1024, 2048, 4096, 8192];
+    pub const SIZE_CLASSES: &'static [usize] = &[32, 64, 128, 256, 512, 1024, 2048, 4096, 8192];
 
     pub fn new(
         manager: Arc<BlockManager>,
         pool: Arc<MemoryPool>,
         stats: Arc<AtomAllocStats>,
-    ) -> Self {
+    ) -> Arc<Self> {
+        let config = manager.config();
+        // One shard per worker thread unless configured, up to `max_caches`
+        let shards = match config.cache_shards {
+            0 => std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+            shards => shards,
+        }
+        .min(config.max_caches);
         let size_classes = Self::SIZE_CLASSES
             .iter()
-            .map(|&size| Arc::new(SizeClass::new(size)))
+            .map(|&size| {
+                Arc::new(SizeClass::new(
+                    size,
+                    config.class_capacity_blocks,
+                    config.class_capacity_bytes,
+                    shards,
+                ))
+            })
             .collect();
 
-        Self {
+        let quarantine = Quarantine::new(config.quarantine_blocks, config.quarantine_bytes);
+        let tracker = AllocationTracker::new(config.track_allocations);
+        let policy = config.cache_policy.build(config.cache_ttl);
+        let background = config.zero_policy == ZeroPolicy::Background;
+
+        Arc::new_cyclic(|cache| Self {
             manager,
             pool,
             size_classes,
+            quarantine,
+            tracker,
+            policy,
+            depot: Self::SIZE_CLASSES.iter().map(|_| SegQueue::new()).collect(),
+            zero_worker: background.then(|| ZeroWorker::spawn(cache.clone())),
             stats,
-        }
+        })
     }
 
     #[inline]
-    fn get_size_class_index(&self, size: usize) -> Option<usize> {
+    pub fn get_size_class_index(&self, size: usize) -> Option<usize> {
         // Fast path for small sizes using trailing zeros
         if size <= 32 {
             return Some(0);
@@ -120,44 +363,417 @@ impl BlockCache {
         }
     }
 
-    pub async fn allocate(&self, size: usize) -> Result<Pin<Arc<Block>>, AtomAllocError> {
-        println!("BlockCache: Attempting allocation of size {}", size);
+    pub fn epoch(&self) -> u64 {
+        self.manager.epoch()
+    }
 
-        if let Some(class_idx) = self.get_size_class_index(size) {
-            if let Some(block) = self.size_classes[class_idx].get_block().await {
-                println!("BlockCache: Found block in size class {}", size);
-                // Need to record allocation even for cached blocks
-                self.stats.record_allocation(block.size()).await;
-                self.stats.record_cache_hit().await;
-                return Ok(block);
-            }
+    pub fn epoch_of(&self, generation: u64) -> u64 {
+        self.manager.epoch_of(generation)
+    }
+
+    // Ties the block to this cache so handles that outlive the caller
+    // (e.g. frozen blocks) can return it, and records who allocated it. A
+    // seal kept through scrubbing is the previous owner's, not the new one's.
+    pub fn adopt(self: &Arc<Self>, block: &Pin<Arc<Block>>, location: &'static Location<'static>) {
+        block.unseal();
+        self.tracker.track(block, location);
+        block.set_home(Arc::downgrade(self));
+        block.set_verify_on_read(
+            cfg!(debug_assertions) && self.manager.config().verify_checksums_on_read,
+        );
+    }
+
+    pub async fn allocate(
+        self: &Arc<Self>,
+        size: usize,
+        location: &'static Location<'static>,
+    ) -> Result<Pin<Arc<Block>>, AtomAllocError> {
+        trace!("BlockCache: Attempting allocation of size {}", size);
+
+        if let Some(block) = self.take_cached(size, location).await? {
+            return Ok(block);
         }
 
-        println!("BlockCache: Cache miss for size {}", size);
+        trace!("BlockCache: Cache miss for size {}", size);
         self.stats.record_cache_miss().await;
 
         let generation = self.manager.new_generation().await;
-        let block = self.pool.allocate_with_generation(size, generation).await?;
+        let block = match self.pool.allocate_with_generation(size, generation).await {
+            Ok(block) => block,
+            Err(AtomAllocError::OutOfMemory) => {
+                // Under memory pressure, quarantined blocks and blocks parked
+                // in task magazines are fair game
+                let released = self.release_quarantine().await + self.release_depot().await;
+                if released == 0 {
+                    return Err(AtomAllocError::OutOfMemory);
+                }
+                trace!("BlockCache: Released quarantine and depot under memory pressure");
+                if let Some(block) = self.take_cached(size, location).await? {
+                    return Ok(block);
+                }
+                self.pool.allocate_with_generation(size, generation).await?
+            }
+            Err(e) => return Err(e),
+        };
+        self.prepare(&block).await?;
         // Pool records its own allocation stats
-        println!("BlockCache: Created new block of size {}", size);
+        trace!("BlockCache: Created new block of size {}", size);
+        self.adopt(&block, location);
+        Ok(block)
+    }
+
+    async fn take_cached(
+        self: &Arc<Self>,
+        size: usize,
+        location: &'static Location<'static>,
+    ) -> Result<Option<Pin<Arc<Block>>>, AtomAllocError> {
+        let Some(class_idx) = self.get_size_class_index(size) else {
+            return Ok(None);
+        };
+
+        // Optionally settle for a block from one of the next larger classes
+        // rather than growing the pool
+        let fallback = self.manager.config().class_fallback;
+        let last_idx = (class_idx + fallback).min(self.size_classes.len() - 1);
+        let mut found = None;
+        for idx in class_idx..=last_idx {
+            if let Some(block) = self.take_from_class(idx).await? {
+                found = Some(block);
+                break;
+            }
+        }
+        let Some(block) = found else {
+            return Ok(None);
+        };
+
+        let waste = block.size() - Self::SIZE_CLASSES[class_idx];
+        if waste > 0 {
+            trace!(
+                "BlockCache: Serving size {} from larger class {}",
+                size,
+                block.size()
+            );
+            self.stats.record_fallback(waste).await;
+        }
+
+        trace!("BlockCache: Found block in size class {}", size);
+        self.prepare(&block).await?;
+        // Need to record allocation even for cached blocks
+        self.stats.record_allocation(block.size()).await;
+        self.stats.record_cache_hit().await;
+        self.adopt(&block, location);
+        Ok(Some(block))
+    }
+
+    // Acquires a block from a size class without losing any it rejects:
+    // blocks of the wrong size go back to the pool, and blocks someone
+    // else holds are kept cached for when they are let go. The latter are
+    // still reported, since they should never have been in the cache.
+    async fn take_from_class(
+        &self,
+        class_idx: usize,
+    ) -> Result<Option<Pin<Arc<Block>>>, BlockError> {
+        let class = &self.size_classes[class_idx];
+        let end = self.policy.on_get(&class.view());
+        let mut in_use = Vec::new();
+
+        // Look at each cached block at most once
+        for _ in 0..class.len().max(1) {
+            let Some((block, stolen)) = class.pop(end) else {
+                break;
+            };
+            if stolen {
+                self.stats.record_steal().await;
+            }
+
+            if block.size() != class.size() {
+                trace!(
+                    "BlockCache: Block of size {} found in size class {}, returning it to the pool",
+                    block.size(),
+                    class.size()
+                );
+                self.pool.release(block).await;
+                continue;
+            }
+
+            self.manager.renew_epoch(&block).await;
+            if !block.try_acquire() {
+                trace!(
+                    "BlockCache: Cached block of size {} is already in use",
+                    class.size()
+                );
+                in_use.push(block);
+                continue;
+            }
+
+            Self::reinsert(class, in_use, end);
+            class.record_acquired();
+            return Ok(Some(block));
+        }
+
+        if !in_use.is_empty() {
+            Self::reinsert(class, in_use, end);
+            return Err(BlockError::InUse);
+        }
+        Ok(None)
+    }
+
+    // Rejected blocks only go back once the scan is over, so it never
+    // meets them twice
+    fn reinsert(class: &SizeClass, blocks: Vec<Pin<Arc<Block>>>, end: CacheEnd) {
+        for block in blocks {
+            class.reinsert(block, end);
+        }
+    }
+
+    // Settles whatever a reused block owes before it is handed out: the
+    // poison check and any zeroing deferred by the zero policy
+    pub async fn prepare(&self, block: &Pin<Arc<Block>>) -> Result<(), AtomAllocError> {
+        self.check_poison(block).await?;
+        self.manager.zero_on_alloc(block).await;
+        Ok(())
+    }
+
+    // A block whose poison pattern was disturbed has a stale handle
+    // writing into it somewhere, so it is taken out of circulation
+    async fn check_poison(&self, block: &Pin<Arc<Block>>) -> Result<(), AtomAllocError> {
+        if let Err(e) = self.manager.check_poison(block).await {
+            trace!("BlockCache: {}", e);
+            self.pool.discard(block.clone()).await;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    // Fresh block from the same size class as `source`, with its contents.
+    // `source` must still be live at `generation`, both before the copy and
+    // once the new block is in hand, or the copy could be of (or be) a
+    // block that has since been reissued.
+    pub async fn allocate_copy(
+        self: &Arc<Self>,
+        source: &Block,
+        generation: u64,
+        location: &'static Location<'static>,
+    ) -> Result<Pin<Arc<Block>>, AtomAllocError> {
+        let size = source.size();
+        source.check_live(generation)?;
+        let data = source.read_consistent(0, size).await?;
+
+        let block = self.allocate(size, location).await?;
+        let copied = match source.check_live(generation) {
+            Ok(()) => block.write(0, &data).await.map_err(AtomAllocError::from),
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = copied {
+            self.deallocate(block).await?;
+            return Err(e);
+        }
+
+        self.stats.record_cow_copy().await;
+        trace!("BlockCache: Copied shared block of size {} on write", size);
         Ok(block)
     }
 
-    pub async fn deallocate(&self, block: Pin<Arc<Block>>) {
+    // Returns a block whose last handle is being dropped to the cache it
+    // came from. Called from `Drop`, so the deallocation, which can wait on
+    // a writer, runs as its own task rather than blocking the caller.
+    pub fn return_home(block: Pin<Arc<Block>>) {
+        let Some(cache) = block.home() else {
+            return;
+        };
+        smol::spawn(async move {
+            if let Err(e) = cache.deallocate(block).await {
+                trace!("BlockCache: Failed to return block: {}", e);
+            }
+        })
+        .detach();
+    }
+
+    pub fn live_allocations(&self) -> Vec<AllocationInfo> {
+        self.tracker.live_allocations()
+    }
+
+    // The checks and cleanup every freed block goes through, wherever it
+    // is parked afterwards. The block leaves under `generation`, so stale
+    // handles are cut off before anyone can acquire it again.
+    pub async fn reclaim(
+        &self,
+        block: &Pin<Arc<Block>>,
+        generation: u64,
+    ) -> Result<(), AtomAllocError> {
+        self.manager.check_release(block)?;
+        block.update_generation(generation);
+        self.tracker.untrack(block);
+        self.manager.zero_block(block).await;
+        Ok(())
+    }
+
+    // Hands a block parked outside the size classes out again; the
+    // counterpart of `reclaim`
+    pub async fn reuse(
+        self: &Arc<Self>,
+        block: &Pin<Arc<Block>>,
+        location: &'static Location<'static>,
+    ) -> Result<(), AtomAllocError> {
+        self.manager.renew_epoch(block).await;
+        if !block.try_acquire() {
+            return Err(BlockError::InUse.into());
+        }
+        self.prepare(block).await?;
+        self.adopt(block, location);
+        Ok(())
+    }
+
+    pub fn depot_put(&self, class_idx: usize, magazine: Vec<Pin<Arc<Block>>>) {
+        self.depot[class_idx].push(magazine);
+    }
+
+    pub fn depot_take(&self, class_idx: usize) -> Option<Vec<Pin<Arc<Block>>>> {
+        self.depot[class_idx].pop()
+    }
+
+    // Gives every block in the depot back to the pool, returning how many
+    // were released. Their frees were recorded when they entered a
+    // magazine. Blocks left over from before a reset are just dropped.
+    pub async fn release_depot(&self) -> usize {
+        let mut released = 0;
+        for magazines in &self.depot {
+            while let Some(magazine) = magazines.pop() {
+                for block in magazine {
+                    if !self.manager.owns(&block) {
+                        continue;
+                    }
+                    self.pool.release(block).await;
+                    released += 1;
+                }
+            }
+        }
+        released
+    }
+
+    pub fn quarantines(&self) -> bool {
+        self.quarantine.is_enabled()
+    }
+
+    pub async fn deallocate(&self, block: Pin<Arc<Block>>) -> Result<(), AtomAllocError> {
         let size = block.size();
-        block.release();
-        self.manager.zero_block(&block).await;
+        let generation = self.manager.new_generation().await;
+        self.reclaim(&block, generation).await?;
 
         if let Some(class_idx) = self.get_size_class_index(size) {
-            println!("BlockCache: Returning block of size {} to cache", size);
-            self.size_classes[class_idx].return_block(block).await;
             self.stats.record_deallocation(size).await;
+            if let Some(worker) = &self.zero_worker {
+                if self.manager.zeroes_in_background(&block) {
+                    trace!("BlockCache: Queueing block of size {} for zeroing", size);
+                    self.stats.record_zeroing(size).await;
+                    // The receiver lives as long as the worker's cache
+                    let _ = worker.sender.send(block).await;
+                    return Ok(());
+                }
+            }
+            self.park(class_idx, block).await;
         } else {
-            println!(
+            trace!(
                 "BlockCache: Block size {} doesn't match any size class, deallocating",
                 size
             );
             self.pool.deallocate(block).await;
         }
+
+        Ok(())
+    }
+
+    // Puts a freed block where the next allocation can find it
+    async fn park(&self, class_idx: usize, block: Pin<Arc<Block>>) {
+        let size = block.size();
+        if self.quarantine.is_enabled() {
+            trace!("BlockCache: Quarantining block of size {}", size);
+            self.quarantine.push(block);
+            self.stats.record_quarantine(size).await;
+            while self.quarantine.is_over_limit() {
+                if !self.release_oldest_quarantined().await {
+                    break;
+                }
+            }
+        } else {
+            trace!("BlockCache: Returning block of size {} to cache", size);
+            self.return_to_class(class_idx, block).await;
+        }
+    }
+
+    // Files a block in its size class, then lets the cache policy shed
+    // whatever has idled too long. Evicted blocks give their memory back
+    // to the pool, and so do blocks the class has no room for.
+    async fn return_to_class(&self, class_idx: usize, block: Pin<Arc<Block>>) {
+        let class = &self.size_classes[class_idx];
+        for block in class.evict(&*self.policy) {
+            trace!("BlockCache: Evicting block of size {}", block.size());
+            self.stats.record_eviction().await;
+            self.pool.release(block).await;
+        }
+
+        if !class.return_block(block.clone(), &*self.policy).await {
+            trace!(
+                "BlockCache: Size class {} declined block, returning it to the pool",
+                block.size()
+            );
+            self.pool.release(block).await;
+        }
+    }
+
+    async fn finish_zeroing(&self, block: Pin<Arc<Block>>) {
+        // Queued before a reset, which already settled its stats
+        if !self.manager.owns(&block) {
+            return;
+        }
+
+        let size = block.size();
+        self.stats.record_zeroing_done(size).await;
+        if let Some(class_idx) = self.get_size_class_index(size) {
+            self.park(class_idx, block).await;
+        }
+    }
+
+    // Moves the oldest quarantined block back into its size class
+    async fn release_oldest_quarantined(&self) -> bool {
+        let Some(block) = self.quarantine.pop() else {
+            return false;
+        };
+
+        let size = block.size();
+        self.stats.record_quarantine_release(size).await;
+        if let Some(class_idx) = self.get_size_class_index(size) {
+            trace!("BlockCache: Releasing quarantined block of size {}", size);
+            self.return_to_class(class_idx, block).await;
+        }
+        true
+    }
+
+    // Empties the quarantine, returning how many blocks were released
+    pub async fn release_quarantine(&self) -> usize {
+        let mut released = 0;
+        while self.release_oldest_quarantined().await {
+            released += 1;
+        }
+        released
+    }
+
+    // Forgets every block: cached, quarantined and parked in the depot
+    // ones are dropped, and ones still handed out are disowned so they
+    // can no longer be freed into the fresh state. Task caches drop their
+    // own leftovers as they come across them.
+    pub async fn reset(&self) {
+        let owner = self.manager.renew_id();
+        self.manager.advance_epoch().await;
+        for class in &self.size_classes {
+            class.clear();
+        }
+        self.quarantine.clear();
+        for magazines in &self.depot {
+            while magazines.pop().is_some() {}
+        }
+        self.tracker.clear();
+        self.pool.reset(owner).await;
+        self.stats.reset().await;
     }
 }
@@ -0,0 +1,42 @@
+// CRC32C (Castagnoli), reflected, table-driven
+const POLY: u32 = 0x82f6_3b78;
+
+const TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+pub(crate) struct Crc32c {
+    state: u32,
+}
+
+impl Crc32c {
+    pub fn new() -> Self {
+        Self { state: !0 }
+    }
+
+    #[inline]
+    pub fn update(&mut self, byte: u8) {
+        let index = (self.state ^ byte as u32) & 0xff;
+        self.state = (self.state >> 8) ^ TABLE[index as usize];
+    }
+
+    pub fn finish(&self) -> u32 {
+        !self.state
+    }
+}
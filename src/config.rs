@@ -1,4 +1,44 @@
-use std::time::Duration;
+use crate::policy::{AdaptivePolicy, CachePolicy, FifoPolicy, LifoPolicy, LruPolicy};
+use std::{sync::Arc, time::Duration};
+
+// Replacement policy for each size class's free blocks
+#[derive(Clone, Debug, Default)]
+pub enum CachePolicyKind {
+    #[default]
+    Lifo,
+    Fifo,
+    Lru,
+    Adaptive,
+    Custom(Arc<dyn CachePolicy>),
+}
+
+impl CachePolicyKind {
+    pub(crate) fn build(&self, ttl: Duration) -> Arc<dyn CachePolicy> {
+        match self {
+            Self::Lifo => Arc::new(LifoPolicy),
+            Self::Fifo => Arc::new(FifoPolicy),
+            Self::Lru => Arc::new(LruPolicy::new(ttl)),
+            Self::Adaptive => Arc::new(AdaptivePolicy::new(ttl)),
+            Self::Custom(policy) => policy.clone(),
+        }
+    }
+}
+
+// When freed memory gets zeroed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ZeroPolicy {
+    // Inline in `deallocate`
+    #[default]
+    OnDealloc,
+    // Every time a cached block is handed out again
+    OnAlloc,
+    // On hand-out, skipping blocks that were never written (`ZEROED_FLAG`)
+    Lazy,
+    // By a worker task; blocks rejoin their size class once clean
+    Background,
+    // Freed contents are left for the next owner to see
+    Never,
+}
 
 #[derive(Clone, Debug)]
 pub struct AtomAllocConfig {
@@ -11,10 +51,23 @@ pub struct AtomAllocConfig {
     // Cache settings
     pub cache_ttl: Duration,
     pub max_caches: usize,
+    pub cache_shards: usize, // per size class, 0 = one per available thread
     pub initial_pool_size: usize,
+    pub cache_policy: CachePolicyKind,
+    pub class_capacity_blocks: usize, // per size class, 0 = no count limit
+    pub class_capacity_bytes: usize,  // per size class, 0 = no byte limit
+    pub class_fallback: usize,        // larger classes tried on a miss, at most 2
+    pub magazine_size: usize,         // blocks per task cache magazine
 
     // Security settings
-    pub zero_on_dealloc: bool,
+    pub zero_policy: ZeroPolicy,
+
+    // Debug settings
+    pub verify_checksums_on_read: bool, // debug builds only
+    pub poison_freed_blocks: bool,
+    pub quarantine_blocks: usize, // 0 = no count limit
+    pub quarantine_bytes: usize,  // 0 = no byte limit
+    pub track_allocations: bool,
 }
 
 impl Default for AtomAllocConfig {
@@ -27,9 +80,21 @@ impl Default for AtomAllocConfig {
 
             cache_ttl: Duration::from_secs(300),
             max_caches: 1000,
+            cache_shards: 0,
             initial_pool_size: 1024 * 1024, // 1MB
+            cache_policy: CachePolicyKind::Lifo,
+            class_capacity_blocks: 0,
+            class_capacity_bytes: 0,
+            class_fallback: 0,
+            magazine_size: 16,
 
-            zero_on_dealloc: true,
+            zero_policy: ZeroPolicy::OnDealloc,
+
+            verify_checksums_on_read: false,
+            poison_freed_blocks: false,
+            quarantine_blocks: 0,
+            quarantine_bytes: 0,
+            track_allocations: false,
         }
     }
 }
@@ -71,6 +136,13 @@ impl AtomAllocConfig {
             ));
         }
 
+        if self.class_fallback > 2 {
+            return Err(format!(
+                "class_fallback ({}) must be <= 2",
+                self.class_fallback
+            ));
+        }
+
         if self.max_caches == 0 {
             return Err("max_caches must be > 0".into());
         }
@@ -86,8 +158,19 @@ impl AtomAllocConfig {
             alignment: 8,
             cache_ttl: Duration::from_secs(60),
             max_caches: 100,
+            cache_shards: 0,
             initial_pool_size: 4 * 1024, // 4KB
-            zero_on_dealloc: true,
+            cache_policy: CachePolicyKind::Lifo,
+            class_capacity_blocks: 0,
+            class_capacity_bytes: 0,
+            class_fallback: 0,
+            magazine_size: 16,
+            zero_policy: ZeroPolicy::OnDealloc,
+            verify_checksums_on_read: false,
+            poison_freed_blocks: false,
+            quarantine_blocks: 0,
+            quarantine_bytes: 0,
+            track_allocations: false,
         }
     }
 }
@@ -0,0 +1,100 @@
+use crate::{
+    block::{number_readers, typed_readers, Block, BlockOps},
+    cache::BlockCache,
+    error::{AtomAllocError, BlockError},
+};
+use std::{panic::Location, pin::Pin, sync::Arc};
+
+// Copy-on-write handle over a shared block. Reads go to the shared block
+// until the first write, which copies it into a private block taken from
+// the same size class. The private copy is returned to the cache on drop.
+// The first write fails if the shared block has been freed since the clone.
+pub struct CowBlock {
+    shared: Pin<Arc<Block>>,
+    generation: u64, // of the shared block when cloned
+    location: &'static Location<'static>,
+    owned: Option<Pin<Arc<Block>>>,
+}
+
+impl CowBlock {
+    pub(crate) fn new(shared: Pin<Arc<Block>>, location: &'static Location<'static>) -> Self {
+        let generation = shared.generation();
+        Self {
+            shared,
+            generation,
+            location,
+            owned: None,
+        }
+    }
+
+    // True until the first write has copied the shared block
+    pub fn is_shared(&self) -> bool {
+        self.owned.is_none()
+    }
+
+    pub fn size(&self) -> usize {
+        self.shared.size()
+    }
+
+    // Reads of the shared block check it is still the one cloned, before
+    // and after loading, so they never see a later owner's data
+    pub async fn read(&self, offset: usize, len: usize) -> Result<Vec<u8>, BlockError> {
+        let Some(owned) = &self.owned else {
+            self.shared.check_live(self.generation)?;
+            let data = self.shared.read(offset, len).await?;
+            self.shared.check_live(self.generation)?;
+            return Ok(data);
+        };
+        owned.read(offset, len).await
+    }
+
+    typed_readers!();
+
+    pub async fn write(&mut self, offset: usize, data: &[u8]) -> Result<(), AtomAllocError> {
+        let size = self.size();
+        if offset + data.len() > size {
+            return Err(BlockError::OutOfBounds {
+                offset,
+                len: data.len(),
+                size,
+            }
+            .into());
+        }
+
+        let block = self.make_owned().await?;
+        block.write(offset, data).await?;
+        Ok(())
+    }
+
+    // The private block, copying the shared one first if needed
+    pub async fn into_block(mut self) -> Result<Pin<Arc<Block>>, AtomAllocError> {
+        self.make_owned().await?;
+        Ok(self.owned.take().expect("owned block was just created"))
+    }
+
+    async fn make_owned(&mut self) -> Result<&Pin<Arc<Block>>, AtomAllocError> {
+        if self.owned.is_none() {
+            let cache = self
+                .shared
+                .home()
+                .ok_or_else(|| AtomAllocError::ManagerError {
+                    message: "shared block has no owning allocator".into(),
+                })?;
+            self.owned = Some(
+                cache
+                    .allocate_copy(&self.shared, self.generation, self.location)
+                    .await?,
+            );
+        }
+
+        Ok(self.owned.as_ref().expect("owned block was just created"))
+    }
+}
+
+impl Drop for CowBlock {
+    fn drop(&mut self) {
+        if let Some(block) = self.owned.take() {
+            BlockCache::return_home(block);
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{
+    block::{for_each_number, Block, BlockOps},
+    error::BlockError,
+};
+use std::mem::size_of;
+
+// Sequential field access on top of the bounds-checked Block read/write.
+// The position only advances when the underlying access succeeds.
+pub struct BlockReader<'a> {
+    block: &'a Block,
+    position: usize,
+}
+
+pub struct BlockWriter<'a> {
+    block: &'a Block,
+    position: usize,
+}
+
+macro_rules! reader_number_accessors {
+    ($ty:ty, $read_le:ident, $read_be:ident, $write_le:ident, $write_be:ident) => {
+        pub async fn $read_le(&mut self) -> Result<$ty, BlockError> {
+            let value = self.block.$read_le(self.position).await?;
+            self.position += size_of::<$ty>();
+            Ok(value)
+        }
+
+        pub async fn $read_be(&mut self) -> Result<$ty, BlockError> {
+            let value = self.block.$read_be(self.position).await?;
+            self.position += size_of::<$ty>();
+            Ok(value)
+        }
+    };
+}
+
+macro_rules! writer_number_accessors {
+    ($ty:ty, $read_le:ident, $read_be:ident, $write_le:ident, $write_be:ident) => {
+        pub async fn $write_le(&mut self, value: $ty) -> Result<(), BlockError> {
+            self.block.$write_le(self.position, value).await?;
+            self.position += size_of::<$ty>();
+            Ok(())
+        }
+
+        pub async fn $write_be(&mut self, value: $ty) -> Result<(), BlockError> {
+            self.block.$write_be(self.position, value).await?;
+            self.position += size_of::<$ty>();
+            Ok(())
+        }
+    };
+}
+
+// Checks that `len` bytes starting at `position` fit in the block
+fn check_advance(block: &Block, position: usize, len: usize) -> Result<(), BlockError> {
+    let size = block.size();
+    if position + len > size {
+        return Err(BlockError::OutOfBounds {
+            offset: position,
+            len,
+            size,
+        });
+    }
+    Ok(())
+}
+
+impl<'a> BlockReader<'a> {
+    pub fn new(block: &'a Block) -> Self {
+        Self { block, position: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: usize) {
+        self.position = position;
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.block.size().saturating_sub(self.position)
+    }
+
+    pub fn skip(&mut self, len: usize) -> Result<(), BlockError> {
+        check_advance(self.block, self.position, len)?;
+        self.position += len;
+        Ok(())
+    }
+
+    pub async fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>, BlockError> {
+        let bytes = self.block.read(self.position, len).await?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    pub async fn read_u8(&mut self) -> Result<u8, BlockError> {
+        let value = self.block.read_u8(self.position).await?;
+        self.position += 1;
+        Ok(value)
+    }
+
+    pub async fn read_i8(&mut self) -> Result<i8, BlockError> {
+        let value = self.block.read_i8(self.position).await?;
+        self.position += 1;
+        Ok(value)
+    }
+
+    for_each_number!(reader_number_accessors);
+}
+
+impl<'a> BlockWriter<'a> {
+    pub fn new(block: &'a Block) -> Self {
+        Self { block, position: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: usize) {
+        self.position = position;
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.block.size().saturating_sub(self.position)
+    }
+
+    pub fn skip(&mut self, len: usize) -> Result<(), BlockError> {
+        check_advance(self.block, self.position, len)?;
+        self.position += len;
+        Ok(())
+    }
+
+    pub async fn write_bytes(&mut self, data: &[u8]) -> Result<(), BlockError> {
+        self.block.write(self.position, data).await?;
+        self.position += data.len();
+        Ok(())
+    }
+
+    pub async fn write_u8(&mut self, value: u8) -> Result<(), BlockError> {
+        self.block.write_u8(self.position, value).await?;
+        self.position += 1;
+        Ok(())
+    }
+
+    pub async fn write_i8(&mut self, value: i8) -> Result<(), BlockError> {
+        self.block.write_i8(self.position, value).await?;
+        self.position += 1;
+        Ok(())
+    }
+
+    for_each_number!(writer_number_accessors);
+}
@@ -15,6 +15,19 @@ pub enum AtomAllocError {
         message: String,
     },
     BlockError(BlockError),
+    UseAfterFree {
+        size: usize,
+        generation: u64,
+        offset: usize,
+    },
+    DoubleFree {
+        size: usize,
+        generation: u64,
+    },
+    ForeignBlock {
+        owner: u64,
+        allocator: u64,
+    },
 }
 
 #[derive(Debug)]
@@ -30,6 +43,17 @@ pub enum BlockError {
         block: u64,
         expected: u64,
     },
+    StaleEpoch {
+        epoch: u64,
+        current: u64,
+    },
+    VersionConflict {
+        current: u64,
+    },
+    ChecksumMismatch {
+        expected: u32,
+        actual: u32,
+    },
 }
 
 impl fmt::Display for AtomAllocError {
@@ -60,6 +84,31 @@ impl fmt::Display for AtomAllocError {
                 write!(f, "Manager error: {}", message)
             }
             Self::BlockError(e) => write!(f, "Block error: {}", e),
+            Self::UseAfterFree {
+                size,
+                generation,
+                offset,
+            } => {
+                write!(
+                    f,
+                    "Freed block (size {}, generation {}) was modified at offset {}",
+                    size, generation, offset
+                )
+            }
+            Self::DoubleFree { size, generation } => {
+                write!(
+                    f,
+                    "Double free of block (size {}, generation {})",
+                    size, generation
+                )
+            }
+            Self::ForeignBlock { owner, allocator } => {
+                write!(
+                    f,
+                    "Block belongs to allocator {}, not allocator {}",
+                    owner, allocator
+                )
+            }
         }
     }
 }
@@ -76,6 +125,9 @@ impl fmt::Display for BlockError {
             }
             Self::NotInitialized => write!(f, "Block not initialized"),
             Self::InUse => write!(f, "Block already in use"),
+            Self::StaleEpoch { epoch, current } => {
+                write!(f, "Handle from epoch {} used in epoch {}", epoch, current)
+            }
             Self::InvalidGeneration { block, expected } => {
                 write!(
                     f,
@@ -83,6 +135,20 @@ impl fmt::Display for BlockError {
                     block, expected
                 )
             }
+            Self::VersionConflict { current } => {
+                write!(
+                    f,
+                    "Block modified concurrently (now at version {})",
+                    current
+                )
+            }
+            Self::ChecksumMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Block checksum mismatch: {:08x} (expected {:08x})",
+                    actual, expected
+                )
+            }
         }
     }
 }
@@ -0,0 +1,52 @@
+use crate::{
+    block::{number_readers, typed_readers, Block, BlockOps},
+    cache::BlockCache,
+    error::BlockError,
+};
+use std::{pin::Pin, sync::Arc};
+
+// Immutable view of a filled block, shared by cloning. There is no write
+// path, so reads skip the seqlock retry of `read_consistent`.
+#[derive(Clone)]
+pub struct FrozenBlock {
+    inner: Arc<FrozenInner>,
+}
+
+struct FrozenInner {
+    block: Pin<Arc<Block>>,
+}
+
+impl FrozenBlock {
+    pub(crate) fn new(block: Pin<Arc<Block>>) -> Self {
+        Self {
+            inner: Arc::new(FrozenInner { block }),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.inner.block.size()
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.inner.block.generation()
+    }
+
+    pub async fn read(&self, offset: usize, len: usize) -> Result<Vec<u8>, BlockError> {
+        let size = self.size();
+        if offset + len > size {
+            return Err(BlockError::OutOfBounds { offset, len, size });
+        }
+
+        self.inner.block.verify_on_read().await?;
+        Ok(self.inner.block.load_bytes(offset, len).await)
+    }
+
+    typed_readers!();
+}
+
+impl Drop for FrozenInner {
+    fn drop(&mut self) {
+        // Last clone gone: hand the block back to the cache it came from
+        BlockCache::return_home(self.block.clone());
+    }
+}
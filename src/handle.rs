@@ -0,0 +1,84 @@
+use crate::{
+    block::{number_readers, number_writers, typed_readers, typed_writers, Block, BlockOps},
+    error::BlockError,
+};
+use std::{pin::Pin, sync::Arc};
+
+// A block reference tied to the generation it was issued under. Blocks
+// get a new generation each time they are freed, so a handle kept past
+// `deallocate` fails with `InvalidGeneration` instead of touching the
+// next owner's data. Handles from before the allocator's current epoch
+// fail with `StaleEpoch`.
+#[derive(Clone)]
+pub struct BlockHandle {
+    block: Pin<Arc<Block>>,
+    generation: u64,
+    epoch: Option<u64>, // None for blocks outside any allocator
+}
+
+impl BlockHandle {
+    pub(crate) fn new(block: Pin<Arc<Block>>) -> Self {
+        let generation = block.generation();
+        let epoch = block.home().map(|cache| cache.epoch_of(generation));
+        Self {
+            block,
+            generation,
+            epoch,
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn epoch(&self) -> Option<u64> {
+        self.epoch
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.check().is_ok()
+    }
+
+    // The underlying block, if it has not been reissued since
+    pub fn check(&self) -> Result<&Pin<Arc<Block>>, BlockError> {
+        if let (Some(epoch), Some(cache)) = (self.epoch, self.block.home()) {
+            let current = cache.epoch();
+            if epoch != current {
+                return Err(BlockError::StaleEpoch { epoch, current });
+            }
+        }
+
+        let current = self.block.generation();
+        if current != self.generation {
+            return Err(BlockError::InvalidGeneration {
+                block: current,
+                expected: self.generation,
+            });
+        }
+        Ok(&self.block)
+    }
+
+    pub fn size(&self) -> usize {
+        self.block.size()
+    }
+
+    pub async fn read(&self, offset: usize, len: usize) -> Result<Vec<u8>, BlockError> {
+        let data = self.check()?.read(offset, len).await?;
+        // The block may have been reissued while the read was in flight
+        self.check()?;
+        Ok(data)
+    }
+
+    // Takes the block's write lease, so it never returns in a task holding
+    // a lease on the block
+    pub async fn write(&self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        // Checked again once the write section is held, as a write cannot
+        // be taken back the way a read can
+        self.check()?
+            .write_checked(self.generation, offset, data)
+            .await
+    }
+
+    typed_readers!();
+    typed_writers!();
+}
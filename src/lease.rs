@@ -0,0 +1,69 @@
+use crate::{
+    block::{number_readers, number_writers, typed_readers, typed_writers, Block},
+    error::BlockError,
+};
+use std::ops::RangeBounds;
+
+// Leases use bits in the block's packed state word and are released on
+// drop. A write lease excludes all other leases, and every public write
+// that does not go through a lease takes the write lease for its
+// duration, so readers holding a read lease never observe a write by the
+// block's users. Only the allocator's own zeroing and poisoning of freed
+// blocks skip it. Those lease-taking writes wait for the lease, so a task
+// holding one must make its changes through the lease itself.
+pub struct BlockWriteLease<'a> {
+    block: &'a Block,
+}
+
+pub struct BlockReadLease<'a> {
+    block: &'a Block,
+}
+
+impl<'a> BlockWriteLease<'a> {
+    pub(crate) fn new(block: &'a Block) -> Self {
+        Self { block }
+    }
+
+    pub async fn write(&self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        self.block.write_leased(offset, data).await
+    }
+
+    pub async fn fill(&self, range: impl RangeBounds<usize>, byte: u8) -> Result<(), BlockError> {
+        self.block.fill_leased(range, byte).await
+    }
+
+    pub async fn clear(&self) {
+        self.block.store_zero().await
+    }
+
+    pub async fn read(&self, offset: usize, len: usize) -> Result<Vec<u8>, BlockError> {
+        self.block.read(offset, len).await
+    }
+
+    typed_readers!();
+    typed_writers!();
+}
+
+impl Drop for BlockWriteLease<'_> {
+    fn drop(&mut self) {
+        self.block.release_write_lease();
+    }
+}
+
+impl<'a> BlockReadLease<'a> {
+    pub(crate) fn new(block: &'a Block) -> Self {
+        Self { block }
+    }
+
+    pub async fn read(&self, offset: usize, len: usize) -> Result<Vec<u8>, BlockError> {
+        self.block.read(offset, len).await
+    }
+
+    typed_readers!();
+}
+
+impl Drop for BlockReadLease<'_> {
+    fn drop(&mut self) {
+        self.block.release_read_lease();
+    }
+}
@@ -1,20 +1,43 @@
-use std::{alloc::Layout, pin::Pin, sync::Arc};
+use std::{alloc::Layout, future::Future, panic::Location, pin::Pin, sync::Arc};
+
+// Per-operation tracing, compiled in with the `trace` feature. Printing
+// through the stdout lock on every allocation would serialize threads.
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        if cfg!(feature = "trace") {
+            println!($($arg)*);
+        }
+    };
+}
 
 pub mod block;
 mod cache;
+mod checksum;
 pub mod config;
+pub mod cow;
+pub mod cursor;
 pub mod error;
+pub mod frozen;
+pub mod handle;
+pub mod lease;
+pub mod magazine;
 mod manager;
+pub mod policy;
 mod pool;
+pub mod slice;
 mod stats;
+mod tracker;
 
 use block::Block;
 use cache::BlockCache;
 use config::AtomAllocConfig;
-use error::AtomAllocError;
+use error::{AtomAllocError, BlockError};
+use handle::BlockHandle;
+use magazine::TaskCache;
 use manager::BlockManager;
 use pool::MemoryPool;
 use stats::AtomAllocStats;
+pub use tracker::AllocationInfo;
 
 pub struct AtomAlloc {
     pool: Arc<MemoryPool>,
@@ -34,13 +57,13 @@ impl AtomAlloc {
 
         let config = Arc::new(config);
         let stats = Arc::new(AtomAllocStats::new().await);
-        let pool = Arc::new(MemoryPool::new(&config, stats.clone()));
         let block_manager = Arc::new(BlockManager::new(&config).await);
-        let cache = Arc::new(BlockCache::new(
-            block_manager.clone(),
-            pool.clone(),
+        let pool = Arc::new(MemoryPool::new(
+            &config,
             stats.clone(),
+            block_manager.allocator_id(),
         ));
+        let cache = BlockCache::new(block_manager.clone(), pool.clone(), stats.clone());
 
         smol::future::yield_now().await;
 
@@ -53,27 +76,100 @@ impl AtomAlloc {
         }
     }
 
-    pub async fn allocate(&self, layout: Layout) -> Result<Pin<Arc<Block>>, AtomAllocError> {
+    // Not an `async fn` so the caller's location can be captured for
+    // allocation tracking
+    #[track_caller]
+    pub fn allocate(
+        &self,
+        layout: Layout,
+    ) -> impl Future<Output = Result<Pin<Arc<Block>>, AtomAllocError>> + '_ {
+        let location = Location::caller();
+        self.allocate_at(layout, location)
+    }
+
+    async fn allocate_at(
+        &self,
+        layout: Layout,
+        location: &'static Location<'static>,
+    ) -> Result<Pin<Arc<Block>>, AtomAllocError> {
         // Try cache first
-        match self.cache.allocate(layout.size()).await {
+        match self.cache.allocate(layout.size(), location).await {
             Ok(block) => {
-                self.block_manager.verify_generation(&block).await?;
                 self.stats.record_cache_hit().await;
                 Ok(block)
             }
+            // A stale handle wrote into a freed block, or a queued block
+            // turned out to be held elsewhere; report it
+            Err(
+                e @ (AtomAllocError::UseAfterFree { .. }
+                | AtomAllocError::BlockError(BlockError::InUse)),
+            ) => Err(e),
             Err(_) => {
                 self.stats.record_cache_miss().await;
                 // Allocate from pool - only pool should record allocation
                 let generation = self.block_manager.new_generation().await;
-                self.pool
+                let block = self
+                    .pool
                     .allocate_with_generation(layout.size(), generation)
-                    .await
+                    .await?;
+                self.cache.prepare(&block).await?;
+                self.cache.adopt(&block, location);
+                Ok(block)
             }
         }
     }
 
-    pub async fn deallocate(&self, block: Pin<Arc<Block>>) {
-        self.cache.deallocate(block).await;
+    pub async fn deallocate(&self, block: Pin<Arc<Block>>) -> Result<(), AtomAllocError> {
+        self.cache.deallocate(block).await
+    }
+
+    #[track_caller]
+    pub fn allocate_handle(
+        &self,
+        layout: Layout,
+    ) -> impl Future<Output = Result<BlockHandle, AtomAllocError>> + '_ {
+        let location = Location::caller();
+        async move { Ok(self.allocate_at(layout, location).await?.handle()) }
+    }
+
+    // Frees the block only if the handle is still current
+    pub async fn deallocate_handle(&self, handle: BlockHandle) -> Result<(), AtomAllocError> {
+        let block = handle.check()?.clone();
+        self.deallocate(block).await
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.block_manager.epoch()
+    }
+
+    // Invalidates every handle issued so far in one step; blocks handed
+    // out from now on carry the new epoch
+    pub async fn advance_epoch(&self) -> u64 {
+        self.block_manager.advance_epoch().await
+    }
+
+    // Wipes the allocator between jobs: every cached block is dropped, the
+    // stats start over, and blocks and handles from before the reset are
+    // invalidated. Outstanding blocks can no longer be deallocated and
+    // their handles fail with `StaleEpoch`. Meant for when no other task
+    // is using the allocator.
+    pub async fn reset(&self) {
+        self.cache.reset().await;
+    }
+
+    // Magazine cache for a single task; move it into the task and allocate
+    // through it there
+    pub fn task_cache(&self) -> TaskCache {
+        TaskCache::new(
+            self.cache.clone(),
+            self.block_manager.clone(),
+            self.stats.clone(),
+        )
+    }
+
+    // Blocks currently handed out, when `track_allocations` is enabled
+    pub fn live_allocations(&self) -> Vec<AllocationInfo> {
+        self.cache.live_allocations()
     }
 
     pub async fn stats(&self) -> Stats {
@@ -83,6 +179,13 @@ impl AtomAlloc {
             current: self.stats.current_bytes().await,
             cache_hits: self.stats.cache_hits().await,
             cache_misses: self.stats.cache_misses().await,
+            cow_copies: self.stats.cow_copies().await,
+            quarantined: self.stats.quarantined_bytes().await,
+            zeroing: self.stats.zeroing_bytes().await,
+            evictions: self.stats.evictions().await,
+            fallbacks: self.stats.fallbacks().await,
+            fallback_waste: self.stats.fallback_waste().await,
+            steals: self.stats.steals().await,
         }
     }
 
@@ -91,6 +194,25 @@ impl AtomAlloc {
     }
 }
 
+impl Drop for AtomAlloc {
+    fn drop(&mut self) {
+        let live = self.live_allocations();
+        if live.is_empty() {
+            return;
+        }
+
+        let bytes: usize = live.iter().map(|info| info.size).sum();
+        eprintln!(
+            "AtomAlloc dropped with {} outstanding blocks ({} bytes):",
+            live.len(),
+            bytes
+        );
+        for info in live {
+            eprintln!("  {}", info);
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Stats {
     pub allocated: usize,
@@ -98,4 +220,11 @@ pub struct Stats {
     pub current: usize,
     pub cache_hits: usize,
     pub cache_misses: usize,
+    pub cow_copies: usize,
+    pub quarantined: usize,
+    pub zeroing: usize,
+    pub evictions: usize,
+    pub fallbacks: usize,
+    pub fallback_waste: usize,
+    pub steals: usize,
 }
@@ -0,0 +1,223 @@
+use crate::{
+    block::{Block, BlockOps},
+    cache::BlockCache,
+    error::{AtomAllocError, BlockError},
+    manager::BlockManager,
+    stats::AtomAllocStats,
+};
+use std::{alloc::Layout, future::Future, mem, ops::Range, panic::Location, pin::Pin, sync::Arc};
+
+type Magazine = Vec<Pin<Arc<Block>>>;
+
+// Stats of magazine hits and frees not yet added to the allocator's
+#[derive(Default)]
+struct PendingCounts {
+    allocated: usize, // bytes
+    freed: usize,     // bytes
+    hits: usize,
+}
+
+impl PendingCounts {
+    fn publish(&mut self, stats: &AtomAllocStats) {
+        let counts = mem::take(self);
+        stats.record_batch(counts.allocated, counts.freed, counts.hits);
+    }
+}
+
+// The two magazines a task keeps for each size class
+#[derive(Default)]
+struct MagazinePair {
+    loaded: Magazine,
+    previous: Magazine,
+}
+
+// Per-task allocation front end in the style of Bonwick's magazine layer.
+// Freed blocks go into small per-class stacks owned by the task, so an
+// allocate/free loop in one task reuses them without going through the
+// shared size classes. Magazines are traded with a global depot when both
+// of a class's magazines are full or empty; anything else falls through
+// to the shared cache. With a quarantine configured, frees skip the
+// magazines so blocks still wait out the quarantine.
+//
+// Magazine hits and frees touch no shared counters: their stats are kept
+// per task and published in batches (see `flush_stats`). Blocks parked in
+// magazines count as freed but keep their share of the memory budget,
+// like blocks cached in a size class, until the depot hands them back to
+// the pool under memory pressure.
+pub struct TaskCache {
+    cache: Arc<BlockCache>,
+    manager: Arc<BlockManager>,
+    stats: Arc<AtomAllocStats>,
+    counts: PendingCounts,
+    magazine_size: usize,
+    classes: Vec<MagazinePair>,
+    generations: Range<u64>,
+}
+
+impl TaskCache {
+    pub(crate) fn new(
+        cache: Arc<BlockCache>,
+        manager: Arc<BlockManager>,
+        stats: Arc<AtomAllocStats>,
+    ) -> Self {
+        let magazine_size = manager.config().magazine_size;
+        let classes = BlockCache::SIZE_CLASSES
+            .iter()
+            .map(|_| MagazinePair::default())
+            .collect();
+        Self {
+            cache,
+            manager,
+            stats,
+            counts: PendingCounts::default(),
+            magazine_size,
+            classes,
+            generations: 0..0,
+        }
+    }
+
+    #[track_caller]
+    pub fn allocate(
+        &mut self,
+        layout: Layout,
+    ) -> impl Future<Output = Result<Pin<Arc<Block>>, AtomAllocError>> + '_ {
+        let location = Location::caller();
+        self.allocate_at(layout, location)
+    }
+
+    async fn allocate_at(
+        &mut self,
+        layout: Layout,
+        location: &'static Location<'static>,
+    ) -> Result<Pin<Arc<Block>>, AtomAllocError> {
+        let size = layout.size();
+        let Some(class_idx) = self.class_index(size) else {
+            return self.cache.allocate(size, location).await;
+        };
+
+        // Blocks someone else grabbed are kept, below the others, for when
+        // they are let go
+        let mut in_use = Vec::new();
+        let mut found = None;
+        while let Some(block) = self.pop(class_idx) {
+            match self.cache.reuse(&block, location).await {
+                Ok(()) => {
+                    found = Some(block);
+                    break;
+                }
+                Err(AtomAllocError::BlockError(BlockError::InUse)) => in_use.push(block),
+                Err(e) => {
+                    self.restore(class_idx, in_use);
+                    return Err(e);
+                }
+            }
+        }
+        self.restore(class_idx, in_use);
+
+        match found {
+            Some(block) => {
+                self.counts.allocated += block.size();
+                self.counts.hits += 1;
+                Ok(block)
+            }
+            None => self.cache.allocate(size, location).await,
+        }
+    }
+
+    fn restore(&mut self, class_idx: usize, blocks: Vec<Pin<Arc<Block>>>) {
+        let loaded = &mut self.classes[class_idx].loaded;
+        loaded.splice(0..0, blocks);
+    }
+
+    pub async fn deallocate(&mut self, block: Pin<Arc<Block>>) -> Result<(), AtomAllocError> {
+        let class_idx = match self.class_index(block.size()) {
+            Some(class_idx) if !self.cache.quarantines() => class_idx,
+            _ => return self.cache.deallocate(block).await,
+        };
+
+        let generation = self.next_generation().await;
+        self.cache.reclaim(&block, generation).await?;
+        self.counts.freed += block.size();
+        self.push(class_idx, block);
+        Ok(())
+    }
+
+    fn class_index(&self, size: usize) -> Option<usize> {
+        if self.magazine_size == 0 {
+            return None;
+        }
+        self.cache.get_size_class_index(size)
+    }
+
+    fn pop(&mut self, class_idx: usize) -> Option<Pin<Arc<Block>>> {
+        loop {
+            let pair = &mut self.classes[class_idx];
+            if pair.loaded.is_empty() {
+                if !pair.previous.is_empty() {
+                    mem::swap(&mut pair.loaded, &mut pair.previous);
+                } else {
+                    // Both empty: trade for a full magazine from the depot
+                    pair.loaded = self.cache.depot_take(class_idx)?;
+                    self.counts.publish(&self.stats);
+                }
+            }
+            let block = pair.loaded.pop()?;
+            // Blocks parked before a reset belong to nobody now
+            if self.manager.owns(&block) {
+                return Some(block);
+            }
+        }
+    }
+
+    fn push(&mut self, class_idx: usize, block: Pin<Arc<Block>>) {
+        let capacity = self.magazine_size;
+        let pair = &mut self.classes[class_idx];
+        if pair.loaded.len() >= capacity {
+            if pair.previous.len() < capacity {
+                mem::swap(&mut pair.loaded, &mut pair.previous);
+            } else {
+                // Both full: the older magazine goes to the depot
+                let full = mem::replace(&mut pair.previous, mem::take(&mut pair.loaded));
+                self.cache.depot_put(class_idx, full);
+                pair.loaded = Vec::with_capacity(capacity);
+                self.counts.publish(&self.stats);
+            }
+        }
+        pair.loaded.push(block);
+    }
+
+    // Publishes the stats of magazine hits and frees so far. They are
+    // otherwise only added to the allocator's stats when a magazine is
+    // traded with the depot and when the task cache is dropped.
+    pub fn flush_stats(&mut self) {
+        self.counts.publish(&self.stats);
+    }
+
+    // Generations come from the manager a magazine's worth at a time. A
+    // batch left over from an earlier epoch is dropped.
+    async fn next_generation(&mut self) -> u64 {
+        if let Some(generation) = self.generations.next() {
+            if self.manager.epoch_of(generation) == self.manager.epoch() {
+                return generation;
+            }
+        }
+        self.generations = self
+            .manager
+            .reserve_generations(self.magazine_size as u64)
+            .await;
+        self.generations.next().unwrap_or_default()
+    }
+}
+
+impl Drop for TaskCache {
+    fn drop(&mut self) {
+        self.flush_stats();
+        for (class_idx, pair) in self.classes.iter_mut().enumerate() {
+            for magazine in [mem::take(&mut pair.loaded), mem::take(&mut pair.previous)] {
+                if !magazine.is_empty() {
+                    self.cache.depot_put(class_idx, magazine);
+                }
+            }
+        }
+    }
+}
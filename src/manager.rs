@@ -1,55 +1,194 @@
 use crate::{
-    block::{Block, BlockOps},
-    config::AtomAllocConfig,
-    error::{AtomAllocError, BlockError},
+    block::{Block, BlockOps, GENERATION_MASK},
+    config::{AtomAllocConfig, ZeroPolicy},
+    error::AtomAllocError,
 };
+use std::ops::Range;
 use std::pin::Pin;
 use std::sync::{
     atomic::{AtomicU64, Ordering},
     Arc,
 };
 
+// Byte written over freed blocks when `poison_freed_blocks` is set
+pub(crate) const POISON_BYTE: u8 = 0xde;
+
+// Allocator ids start at 1 so untagged blocks (0) are always foreign
+static NEXT_ALLOCATOR_ID: AtomicU64 = AtomicU64::new(1);
+
+// Generations come from a clock holding an epoch above a 32-bit counter.
+// Blocks store the low 48 bits, i.e. the counter and the low 16 bits of the
+// epoch. When the counter runs out it rolls over into the next epoch
+// instead of wrapping, so a generation never repeats within an epoch, and
+// handles remember their full epoch so they cannot alias an older block.
+const COUNTER_BITS: u32 = 32;
+const COUNTER_MASK: u64 = (1 << COUNTER_BITS) - 1;
+const EPOCH_TAG_BITS: u32 = GENERATION_MASK.count_ones() - COUNTER_BITS;
+
 pub(crate) struct BlockManager {
     config: Arc<AtomAllocConfig>,
-    current_generation: AtomicU64,
+    clock: AtomicU64,
+    allocator_id: AtomicU64,
 }
 
 impl BlockManager {
     pub async fn new(config: &AtomAllocConfig) -> Self {
         Self {
             config: Arc::new(config.clone()),
-            current_generation: AtomicU64::new(0),
+            clock: AtomicU64::new(0),
+            allocator_id: AtomicU64::new(NEXT_ALLOCATOR_ID.fetch_add(1, Ordering::Relaxed)),
         }
     }
 
-    pub async fn verify_generation(&self, block: &Pin<Arc<Block>>) -> Result<(), AtomAllocError> {
-        // Don't verify if zeroing is disabled
-        if !self.config.zero_on_dealloc {
-            return Ok(());
-        }
+    pub fn allocator_id(&self) -> u64 {
+        self.allocator_id.load(Ordering::Acquire)
+    }
+
+    // Takes a fresh allocator id, so every block stamped with the old one
+    // is treated as foreign from now on
+    pub fn renew_id(&self) -> u64 {
+        let id = NEXT_ALLOCATOR_ID.fetch_add(1, Ordering::Relaxed);
+        self.allocator_id.store(id, Ordering::Release);
+        id
+    }
+
+    // Whether a block was stamped under the current allocator id
+    pub fn owns(&self, block: &Block) -> bool {
+        block.owner() == self.allocator_id()
+    }
 
-        // Get generation when block was created
-        let block_gen = block.generation();
+    // Rejects blocks from other allocators and blocks already released
+    pub fn check_release(&self, block: &Block) -> Result<(), AtomAllocError> {
+        let owner = block.owner();
+        let allocator = self.allocator_id();
+        if owner != allocator {
+            return Err(AtomAllocError::ForeignBlock { owner, allocator });
+        }
 
-        // Should be less than or equal to current generation
-        let current_gen = self.current_generation.load(Ordering::Acquire);
-        if block_gen > current_gen {
-            return Err(AtomAllocError::BlockError(BlockError::InvalidGeneration {
-                block: block_gen,
-                expected: current_gen,
-            }));
+        if !block.try_release() {
+            return Err(AtomAllocError::DoubleFree {
+                size: block.size(),
+                generation: block.generation(),
+            });
         }
 
         Ok(())
     }
 
+    pub fn config(&self) -> &AtomAllocConfig {
+        &self.config
+    }
+
     pub async fn new_generation(&self) -> u64 {
-        self.current_generation.fetch_add(1, Ordering::AcqRel)
+        self.reserve_generations(1).await.start
+    }
+
+    // A batch of generations for a task cache to hand out on its own. A
+    // batch never straddles epochs; one that doesn't fit in what is left
+    // of the counter starts the next epoch instead.
+    pub async fn reserve_generations(&self, count: u64) -> Range<u64> {
+        let count = count.clamp(1, COUNTER_MASK + 1);
+        let mut start = 0;
+        let _ = self
+            .clock
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |clock| {
+                start = if (clock & COUNTER_MASK) + count <= COUNTER_MASK + 1 {
+                    clock
+                } else {
+                    (clock | COUNTER_MASK).wrapping_add(1)
+                };
+                Some(start.wrapping_add(count))
+            });
+        start..start.saturating_add(count)
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.clock.load(Ordering::Acquire) >> COUNTER_BITS
+    }
+
+    // Starts a new epoch, returning its number. Handles issued in earlier
+    // epochs stop validating.
+    pub async fn advance_epoch(&self) -> u64 {
+        let previous = self
+            .clock
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |clock| {
+                Some((clock | COUNTER_MASK).wrapping_add(1))
+            })
+            .unwrap_or_default();
+        (previous >> COUNTER_BITS).wrapping_add(1)
+    }
+
+    // Full epoch of a generation stamped on a block, which only keeps its
+    // low `EPOCH_TAG_BITS`. Assumes the stamp is fewer than 2^EPOCH_TAG_BITS
+    // epochs old; anything older is long stale either way.
+    pub fn epoch_of(&self, generation: u64) -> u64 {
+        let current = self.epoch();
+        let tag = (generation & GENERATION_MASK) >> COUNTER_BITS;
+        let behind = current.wrapping_sub(tag) & ((1 << EPOCH_TAG_BITS) - 1);
+        current.wrapping_sub(behind)
+    }
+
+    // Blocks get a fresh generation as they are freed, so handles taken
+    // before no longer match them. One freed in an earlier epoch is
+    // restamped in the current one before it is handed out again.
+    pub async fn renew_epoch(&self, block: &Block) {
+        if self.epoch_of(block.generation()) != self.epoch() {
+            let generation = self.new_generation().await;
+            block.update_generation(generation);
+        }
     }
 
     pub async fn zero_block(&self, block: &Pin<Arc<Block>>) {
-        if self.config.zero_on_dealloc {
-            block.clear().await;
+        if self.config.poison_freed_blocks {
+            // Poison instead of zeroing so stale writes can be detected
+            block.scrub(POISON_BYTE).await;
+        } else if self.config.zero_policy == ZeroPolicy::OnDealloc {
+            block.scrub(0).await;
+        }
+    }
+
+    // Freed blocks that should go to the zeroing worker rather than
+    // straight back into their size class
+    pub fn zeroes_in_background(&self, block: &Block) -> bool {
+        !self.config.poison_freed_blocks
+            && self.config.zero_policy == ZeroPolicy::Background
+            && !block.is_zeroed()
+    }
+
+    // Zeroing owed by a block about to be handed out again. Poisoned
+    // blocks are already cleared by `check_poison`.
+    pub async fn zero_on_alloc(&self, block: &Pin<Arc<Block>>) {
+        if self.config.poison_freed_blocks {
+            return;
         }
+
+        match self.config.zero_policy {
+            ZeroPolicy::OnAlloc => block.store_zero().await,
+            // Background zeroing is skipped by blocks parked in task
+            // magazines, so catch those here
+            ZeroPolicy::Lazy | ZeroPolicy::Background if !block.is_zeroed() => {
+                block.store_zero().await
+            }
+            _ => {}
+        }
+    }
+
+    // Checks that a block coming back out of the cache still carries the
+    // poison pattern it was freed with, then zeroes it for its new owner
+    pub async fn check_poison(&self, block: &Pin<Arc<Block>>) -> Result<(), AtomAllocError> {
+        if !self.config.poison_freed_blocks || block.is_zeroed() {
+            return Ok(());
+        }
+
+        if let Some(offset) = block.find_mismatch(POISON_BYTE).await {
+            return Err(AtomAllocError::UseAfterFree {
+                size: block.size(),
+                generation: block.generation(),
+                offset,
+            });
+        }
+
+        block.store_zero().await;
+        Ok(())
     }
 }
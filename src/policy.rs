@@ -0,0 +1,120 @@
+use std::{fmt, time::Duration};
+
+// Snapshot of a size class handed to a `CachePolicy`
+#[derive(Debug, Clone, Copy)]
+pub struct CacheView {
+    pub block_size: usize,
+    pub cached: usize,
+    pub allocations: usize,
+    // Smoothed time between allocations served from the class
+    pub mean_interval: Option<Duration>,
+}
+
+// Which cached block an allocation takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheEnd {
+    Newest,
+    Oldest,
+}
+
+// Decides how a size class reuses and sheds its free blocks. Blocks are
+// kept in the order they were returned; the policy picks the end to
+// allocate from and whether the oldest block has idled long enough to be
+// given back to the pool.
+pub trait CachePolicy: Send + Sync + fmt::Debug {
+    fn on_get(&self, view: &CacheView) -> CacheEnd;
+
+    // Whether a freed block should be cached at all
+    fn on_return(&self, _view: &CacheView) -> bool {
+        true
+    }
+
+    fn should_evict(&self, view: &CacheView, idle: Duration) -> bool;
+}
+
+// Most recently freed block first, for the warmest cache lines
+#[derive(Debug, Default)]
+pub struct LifoPolicy;
+
+impl CachePolicy for LifoPolicy {
+    fn on_get(&self, _view: &CacheView) -> CacheEnd {
+        CacheEnd::Newest
+    }
+
+    fn should_evict(&self, _view: &CacheView, _idle: Duration) -> bool {
+        false
+    }
+}
+
+// Oldest freed block first, spreading reuse across the cache
+#[derive(Debug, Default)]
+pub struct FifoPolicy;
+
+impl CachePolicy for FifoPolicy {
+    fn on_get(&self, _view: &CacheView) -> CacheEnd {
+        CacheEnd::Oldest
+    }
+
+    fn should_evict(&self, _view: &CacheView, _idle: Duration) -> bool {
+        false
+    }
+}
+
+// Reuses the most recently freed block and evicts blocks left unused
+// for longer than `ttl`
+#[derive(Debug)]
+pub struct LruPolicy {
+    ttl: Duration,
+}
+
+impl LruPolicy {
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl }
+    }
+}
+
+impl CachePolicy for LruPolicy {
+    fn on_get(&self, _view: &CacheView) -> CacheEnd {
+        CacheEnd::Newest
+    }
+
+    fn should_evict(&self, _view: &CacheView, idle: Duration) -> bool {
+        idle > self.ttl
+    }
+}
+
+// LRU whose idle limit follows demand: a block that has sat through the
+// time the class would need to use up its whole cache several times over
+// is surplus, even well inside `ttl`
+#[derive(Debug)]
+pub struct AdaptivePolicy {
+    ttl: Duration,
+}
+
+impl AdaptivePolicy {
+    const SLACK: u32 = 4;
+
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl }
+    }
+}
+
+impl CachePolicy for AdaptivePolicy {
+    fn on_get(&self, _view: &CacheView) -> CacheEnd {
+        CacheEnd::Newest
+    }
+
+    fn should_evict(&self, view: &CacheView, idle: Duration) -> bool {
+        if idle > self.ttl {
+            return true;
+        }
+
+        match view.mean_interval {
+            Some(interval) => {
+                let cached = view.cached.clamp(1, u32::MAX as usize) as u32;
+                idle > interval.saturating_mul(cached).saturating_mul(Self::SLACK)
+            }
+            None => false,
+        }
+    }
+}
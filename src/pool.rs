@@ -1,13 +1,13 @@
 use crate::{
     block::{Block, BlockOps},
     config::AtomAllocConfig,
-    error::AtomAllocError,
+    error::{AtomAllocError, BlockError},
     stats::AtomAllocStats,
 };
 use crossbeam::queue::SegQueue;
 use std::pin::Pin;
 use std::sync::{
-    atomic::{AtomicUsize, Ordering},
+    atomic::{AtomicU64, AtomicUsize, Ordering},
     Arc,
 };
 
@@ -16,6 +16,8 @@ pub struct MemoryPool {
     stats: Arc<AtomAllocStats>,
     config: Arc<AtomAllocConfig>,
     total_memory: AtomicUsize,
+    free_bytes: AtomicUsize, // held in free lists, outside `total_memory`
+    owner: AtomicU64,        // allocator id stamped on every block
 }
 
 struct SizePool {
@@ -45,13 +47,15 @@ impl SizePool {
 }
 
 impl MemoryPool {
-    pub fn new(config: &AtomAllocConfig, stats: Arc<AtomAllocStats>) -> Self {
+    pub fn new(config: &AtomAllocConfig, stats: Arc<AtomAllocStats>, owner: u64) -> Self {
         let pools = Self::create_size_pools(config);
         Self {
             pools,
             stats,
             config: Arc::new(config.clone()),
             total_memory: AtomicUsize::new(0),
+            free_bytes: AtomicUsize::new(0),
+            owner: AtomicU64::new(owner),
         }
     }
 
@@ -101,77 +105,156 @@ impl MemoryPool {
         let pool = self.get_size_pool(size)?;
         let actual_size = pool.block_size;
 
-        // Get current total memory atomically
-        let current_total = self.total_memory.load(Ordering::Acquire);
-        println!(
-            "Memory check - current: {}, requesting: {} (rounded to {}), max: {}",
-            current_total, size, actual_size, self.config.max_memory
-        );
-
-        // Leave some buffer space to prevent exact max allocation
-        let effective_max = (self.config.max_memory * 3) / 4; // 75% of max
-        if current_total + actual_size > effective_max {
-            println!(
-                "Would exceed effective memory limit: {} + {} > {}",
-                current_total, actual_size, effective_max
-            );
-            return Err(AtomAllocError::OutOfMemory);
-        }
+        // Reserve the memory before handing out any block, reused or new
+        self.reserve(actual_size)?;
 
         // Try to get a free block first
         if let Some(block) = pool.get_free_block() {
-            if block.try_acquire() {
-                println!("Reused block from pool of size {}", actual_size);
-                pool.allocated_blocks.fetch_add(1, Ordering::Relaxed);
-                // Don't record allocation stats here - let cache do it
-                return Ok(block);
+            // Restamped before it is usable, so no stale handle matches
+            block.update_generation(generation);
+            if !block.try_acquire() {
+                // Still held elsewhere; keep it for when it is let go
+                pool.push_free_block(block);
+                self.unreserve(actual_size);
+                return Err(BlockError::InUse.into());
             }
+            self.free_bytes.fetch_sub(actual_size, Ordering::AcqRel);
+            trace!("Reused block from pool of size {}", actual_size);
+            self.stats.record_allocation(actual_size).await;
+            pool.allocated_blocks.fetch_add(1, Ordering::Relaxed);
+            return Ok(block);
         }
 
-        // Actually reserve the memory
-        let new_total = current_total + actual_size;
-        match self.total_memory.compare_exchange(
-            current_total,
-            new_total,
-            Ordering::AcqRel,
-            Ordering::Acquire,
-        ) {
-            Ok(_) => {
-                println!(
+        // Make room for the new block among the free ones
+        self.trim_free();
+        let block = Block::new(actual_size, generation);
+        block.set_owner(self.owner.load(Ordering::Acquire));
+        block.try_acquire();
+        self.stats.record_allocation(actual_size).await;
+        pool.total_blocks.fetch_add(1, Ordering::Relaxed);
+        pool.allocated_blocks.fetch_add(1, Ordering::Relaxed);
+        Ok(block)
+    }
+
+    // Claims `bytes` of the budget, retrying when other reservations race
+    // this one; only a budget that is actually exhausted fails
+    fn reserve(&self, bytes: usize) -> Result<(), AtomAllocError> {
+        // Leave some buffer space to prevent exact max allocation
+        let effective_max = (self.config.max_memory * 3) / 4; // 75% of max
+        match self
+            .total_memory
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                current
+                    .checked_add(bytes)
+                    .filter(|&total| total <= effective_max)
+            }) {
+            Ok(previous) => {
+                trace!(
                     "Reserved {} bytes (actual size), total now: {}",
-                    actual_size, new_total
+                    bytes,
+                    previous + bytes
                 );
-                let block = Block::new(actual_size, generation);
-                self.stats.record_allocation(actual_size).await;
-                pool.total_blocks.fetch_add(1, Ordering::Relaxed);
-                pool.allocated_blocks.fetch_add(1, Ordering::Relaxed);
-                Ok(block)
+                Ok(())
             }
             Err(current) => {
-                println!(
-                    "Memory reservation failed, current total is now: {}",
-                    current
+                trace!(
+                    "Would exceed effective memory limit: {} + {} > {}",
+                    current,
+                    bytes,
+                    effective_max
                 );
                 Err(AtomAllocError::OutOfMemory)
             }
         }
     }
 
+    fn unreserve(&self, bytes: usize) -> usize {
+        self.total_memory.fetch_sub(bytes, Ordering::AcqRel)
+    }
+
     pub async fn deallocate(&self, block: Pin<Arc<Block>>) {
         let size = block.size();
-        if let Ok(pool) = self.get_size_pool(size) {
-            let old_total = self.total_memory.fetch_sub(size, Ordering::Release);
-            println!(
-                "Deallocated {} bytes, old total: {}, new total: {}",
-                size,
-                old_total,
-                old_total - size
-            );
-
-            block.release();
-            pool.push_free_block(block);
+        if self.release(block).await {
             self.stats.record_deallocation(size).await;
-            pool.allocated_blocks.fetch_sub(1, Ordering::Relaxed);
         }
     }
+
+    // Takes back a block whose deallocation was already recorded, e.g. one
+    // a size class had no room for, freeing its share of the budget
+    pub async fn release(&self, block: Pin<Arc<Block>>) -> bool {
+        let size = block.size();
+        let Ok(pool) = self.get_size_pool(size) else {
+            return false;
+        };
+
+        let old_total = self.unreserve(size);
+        trace!(
+            "Deallocated {} bytes, old total: {}, new total: {}",
+            size,
+            old_total,
+            old_total - size
+        );
+
+        block.release();
+        pool.allocated_blocks.fetch_sub(1, Ordering::Relaxed);
+        if self.keep_free(size) {
+            pool.push_free_block(block);
+        } else {
+            trace!("Dropped released block of size {}", size);
+            pool.total_blocks.fetch_sub(1, Ordering::Relaxed);
+        }
+        true
+    }
+
+    // Free blocks are kept for reuse only while they and the reserved
+    // memory together stay within `max_memory`
+    fn keep_free(&self, size: usize) -> bool {
+        self.free_bytes
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |free| {
+                let total = self.total_memory.load(Ordering::Acquire);
+                (total + free + size <= self.config.max_memory).then_some(free + size)
+            })
+            .is_ok()
+    }
+
+    // Drops free blocks until they fit next to the reserved memory again
+    fn trim_free(&self) {
+        for pool in &self.pools {
+            while self.total_memory.load(Ordering::Acquire)
+                + self.free_bytes.load(Ordering::Acquire)
+                > self.config.max_memory
+            {
+                if pool.get_free_block().is_none() {
+                    break;
+                }
+                self.free_bytes.fetch_sub(pool.block_size, Ordering::AcqRel);
+                pool.total_blocks.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // Drops a block that must never be handed out again, releasing its
+    // share of the memory budget. Stats were already settled on free.
+    pub async fn discard(&self, block: Pin<Arc<Block>>) {
+        let size = block.size();
+        if let Ok(pool) = self.get_size_pool(size) {
+            self.unreserve(size);
+            pool.total_blocks.fetch_sub(1, Ordering::Relaxed);
+            trace!("Discarded block of size {}", size);
+        }
+    }
+
+    // Drops every free block and forgets the budget held by blocks still
+    // handed out; those are stamped with `owner`'s predecessor and can no
+    // longer be given back
+    pub async fn reset(&self, owner: u64) {
+        self.owner.store(owner, Ordering::Release);
+        for pool in &self.pools {
+            while pool.get_free_block().is_some() {}
+            pool.allocated_blocks.store(0, Ordering::Relaxed);
+            pool.total_blocks.store(0, Ordering::Relaxed);
+        }
+        self.total_memory.store(0, Ordering::Release);
+        self.free_bytes.store(0, Ordering::Release);
+    }
 }
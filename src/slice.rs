@@ -0,0 +1,87 @@
+use crate::{
+    block::{
+        number_readers, number_writers, resolve_range, typed_readers, typed_writers, Block,
+        BlockOps,
+    },
+    error::BlockError,
+};
+use std::{ops::RangeBounds, pin::Pin, sync::Arc};
+
+// A sub-range of a block. Offsets are rebased to the start of the slice
+// and every access is bounds-checked against the slice, not the block.
+// The parent block stays alive until all of its slices are dropped.
+#[derive(Clone)]
+pub struct BlockSlice {
+    block: Pin<Arc<Block>>,
+    start: usize,
+    len: usize,
+}
+
+impl BlockSlice {
+    pub(crate) fn new(
+        block: Pin<Arc<Block>>,
+        range: impl RangeBounds<usize>,
+    ) -> Result<Self, BlockError> {
+        let size = block.size();
+        let range = resolve_range(range, size)?;
+        Ok(Self {
+            block,
+            start: range.start,
+            len: range.end - range.start,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Offset of this slice within the parent block
+    pub fn offset(&self) -> usize {
+        self.start
+    }
+
+    pub fn block(&self) -> &Pin<Arc<Block>> {
+        &self.block
+    }
+
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Result<BlockSlice, BlockError> {
+        let range = resolve_range(range, self.len)?;
+        Ok(Self {
+            block: self.block.clone(),
+            start: self.start + range.start,
+            len: range.end - range.start,
+        })
+    }
+
+    pub fn split_at(&self, mid: usize) -> Result<(BlockSlice, BlockSlice), BlockError> {
+        Ok((self.slice(..mid)?, self.slice(mid..)?))
+    }
+
+    fn check_bounds(&self, offset: usize, len: usize) -> Result<(), BlockError> {
+        if offset + len > self.len {
+            return Err(BlockError::OutOfBounds {
+                offset,
+                len,
+                size: self.len,
+            });
+        }
+        Ok(())
+    }
+
+    pub async fn write(&self, offset: usize, data: &[u8]) -> Result<(), BlockError> {
+        self.check_bounds(offset, data.len())?;
+        self.block.write(self.start + offset, data).await
+    }
+
+    pub async fn read(&self, offset: usize, len: usize) -> Result<Vec<u8>, BlockError> {
+        self.check_bounds(offset, len)?;
+        self.block.read(self.start + offset, len).await
+    }
+
+    typed_readers!();
+    typed_writers!();
+}
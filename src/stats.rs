@@ -6,6 +6,13 @@ pub struct AtomAllocStats {
     current_allocated: AtomicUsize,
     cache_hits: AtomicUsize,
     cache_misses: AtomicUsize,
+    cow_copies: AtomicUsize,
+    quarantined_bytes: AtomicUsize,
+    zeroing_bytes: AtomicUsize,
+    evictions: AtomicUsize,
+    fallbacks: AtomicUsize,
+    fallback_waste: AtomicUsize,
+    steals: AtomicUsize,
 }
 
 impl AtomAllocStats {
@@ -16,6 +23,13 @@ impl AtomAllocStats {
             current_allocated: AtomicUsize::new(0),
             cache_hits: AtomicUsize::new(0),
             cache_misses: AtomicUsize::new(0),
+            cow_copies: AtomicUsize::new(0),
+            quarantined_bytes: AtomicUsize::new(0),
+            zeroing_bytes: AtomicUsize::new(0),
+            evictions: AtomicUsize::new(0),
+            fallbacks: AtomicUsize::new(0),
+            fallback_waste: AtomicUsize::new(0),
+            steals: AtomicUsize::new(0),
         }
     }
 
@@ -23,14 +37,14 @@ impl AtomAllocStats {
     pub async fn record_allocation(&self, size: usize) {
         let prev_total = self.total_allocated.fetch_add(size, Ordering::Release);
         let prev_current = self.current_allocated.fetch_add(size, Ordering::Release);
-        println!("Recording allocation: prev_total={}, prev_current={}, size={}, new_total={}, new_current={}",
+        trace!("Recording allocation: prev_total={}, prev_current={}, size={}, new_total={}, new_current={}",
                 prev_total, prev_current, size, prev_total + size, prev_current + size);
     }
 
     pub async fn record_deallocation(&self, size: usize) {
         let prev_freed = self.total_freed.fetch_add(size, Ordering::Release);
         let prev_current = self.current_allocated.fetch_sub(size, Ordering::Release);
-        println!("Recording deallocation: prev_freed={}, prev_current={}, size={}, new_freed={}, new_current={}",
+        trace!("Recording deallocation: prev_freed={}, prev_current={}, size={}, new_freed={}, new_current={}",
                 prev_freed, prev_current, size, prev_freed + size, prev_current - size);
     }
 
@@ -44,6 +58,75 @@ impl AtomAllocStats {
         smol::future::yield_now().await;
     }
 
+    pub async fn record_cow_copy(&self) {
+        self.cow_copies.fetch_add(1, Ordering::Release);
+        smol::future::yield_now().await;
+    }
+
+    pub async fn record_quarantine(&self, size: usize) {
+        self.quarantined_bytes.fetch_add(size, Ordering::Release);
+    }
+
+    pub async fn record_quarantine_release(&self, size: usize) {
+        self.quarantined_bytes.fetch_sub(size, Ordering::Release);
+    }
+
+    pub async fn record_zeroing(&self, size: usize) {
+        self.zeroing_bytes.fetch_add(size, Ordering::Release);
+    }
+
+    pub async fn record_zeroing_done(&self, size: usize) {
+        self.zeroing_bytes.fetch_sub(size, Ordering::Release);
+    }
+
+    pub async fn record_eviction(&self) {
+        self.evictions.fetch_add(1, Ordering::Release);
+    }
+
+    // A request served from a larger size class, wasting `waste` bytes
+    pub async fn record_fallback(&self, waste: usize) {
+        self.fallbacks.fetch_add(1, Ordering::Release);
+        self.fallback_waste.fetch_add(waste, Ordering::Release);
+    }
+
+    // Stats a task cache gathered on its own, added in one go
+    pub fn record_batch(&self, allocated: usize, freed: usize, hits: usize) {
+        if allocated + freed + hits == 0 {
+            return;
+        }
+        self.total_allocated.fetch_add(allocated, Ordering::Release);
+        self.total_freed.fetch_add(freed, Ordering::Release);
+        self.current_allocated
+            .fetch_add(allocated, Ordering::Release);
+        self.current_allocated.fetch_sub(freed, Ordering::Release);
+        self.cache_hits.fetch_add(hits, Ordering::Release);
+    }
+
+    // A cached block taken from another thread's shard
+    pub async fn record_steal(&self) {
+        self.steals.fetch_add(1, Ordering::Release);
+    }
+
+    // Zeroes every counter
+    pub async fn reset(&self) {
+        for counter in [
+            &self.total_allocated,
+            &self.total_freed,
+            &self.current_allocated,
+            &self.cache_hits,
+            &self.cache_misses,
+            &self.cow_copies,
+            &self.quarantined_bytes,
+            &self.zeroing_bytes,
+            &self.evictions,
+            &self.fallbacks,
+            &self.fallback_waste,
+            &self.steals,
+        ] {
+            counter.store(0, Ordering::Release);
+        }
+    }
+
     // Stats retrieval
     pub async fn allocated_bytes(&self) -> usize {
         let result = self.total_allocated.load(Ordering::Acquire);
@@ -74,4 +157,46 @@ impl AtomAllocStats {
         smol::future::yield_now().await;
         result
     }
+
+    pub async fn cow_copies(&self) -> usize {
+        let result = self.cow_copies.load(Ordering::Acquire);
+        smol::future::yield_now().await;
+        result
+    }
+
+    pub async fn quarantined_bytes(&self) -> usize {
+        let result = self.quarantined_bytes.load(Ordering::Acquire);
+        smol::future::yield_now().await;
+        result
+    }
+
+    pub async fn zeroing_bytes(&self) -> usize {
+        let result = self.zeroing_bytes.load(Ordering::Acquire);
+        smol::future::yield_now().await;
+        result
+    }
+
+    pub async fn evictions(&self) -> usize {
+        let result = self.evictions.load(Ordering::Acquire);
+        smol::future::yield_now().await;
+        result
+    }
+
+    pub async fn fallbacks(&self) -> usize {
+        let result = self.fallbacks.load(Ordering::Acquire);
+        smol::future::yield_now().await;
+        result
+    }
+
+    pub async fn fallback_waste(&self) -> usize {
+        let result = self.fallback_waste.load(Ordering::Acquire);
+        smol::future::yield_now().await;
+        result
+    }
+
+    pub async fn steals(&self) -> usize {
+        let result = self.steals.load(Ordering::Acquire);
+        smol::future::yield_now().await;
+        result
+    }
 }
@@ -0,0 +1,82 @@
+use crate::block::{Block, BlockOps};
+use crossbeam_skiplist::SkipMap;
+use std::{
+    fmt,
+    panic::Location,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+// A block that has been handed out and not yet returned
+#[derive(Debug, Clone)]
+pub struct AllocationInfo {
+    pub size: usize,
+    pub generation: u64,
+    pub allocated_at: Instant,
+    pub location: &'static Location<'static>,
+}
+
+impl fmt::Display for AllocationInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes (generation {}) allocated at {} {:?} ago",
+            self.size,
+            self.generation,
+            self.location,
+            self.allocated_at.elapsed()
+        )
+    }
+}
+
+// Opt-in registry of live allocations, keyed by an id stored on the block
+pub(crate) struct AllocationTracker {
+    enabled: bool,
+    next_id: AtomicU64,
+    live: SkipMap<u64, AllocationInfo>,
+}
+
+impl AllocationTracker {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            next_id: AtomicU64::new(1),
+            live: SkipMap::new(),
+        }
+    }
+
+    pub fn track(&self, block: &Block, location: &'static Location<'static>) {
+        if !self.enabled {
+            return;
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.live.insert(
+            id,
+            AllocationInfo {
+                size: block.size(),
+                generation: block.generation(),
+                allocated_at: Instant::now(),
+                location,
+            },
+        );
+        block.set_allocation_id(id);
+    }
+
+    pub fn untrack(&self, block: &Block) {
+        if let Some(id) = block.take_allocation_id() {
+            self.live.remove(&id);
+        }
+    }
+
+    pub fn clear(&self) {
+        self.live.clear();
+    }
+
+    pub fn live_allocations(&self) -> Vec<AllocationInfo> {
+        self.live
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+}
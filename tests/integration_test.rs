@@ -1,7 +1,22 @@
-use atomalloc::{block::BlockOps, config::AtomAllocConfig, error::AtomAllocError, AtomAlloc};
+use atomalloc::{
+    block::{Block, BlockOps},
+    config::{AtomAllocConfig, CachePolicyKind, ZeroPolicy},
+    error::{AtomAllocError, BlockError},
+    policy::{CacheEnd, CachePolicy, CacheView},
+    AtomAlloc,
+};
 use macro_rules_attribute::apply;
 use smol_macros::{test, Executor};
-use std::{alloc::Layout, sync::Arc, time::Duration};
+use std::{
+    alloc::Layout,
+    ops::Bound,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 #[apply(test!)]
 async fn test_basic_alloc_dealloc(ex: &Executor<'_>) {
@@ -23,7 +38,7 @@ async fn test_basic_alloc_dealloc(ex: &Executor<'_>) {
         assert_eq!(block.size(), 64);
 
         // Deallocate
-        allocator.deallocate(block).await;
+        allocator.deallocate(block).await.unwrap();
         let stats_final = allocator.stats().await;
         println!("Final stats: {:?}", stats_final);
 
@@ -44,7 +59,7 @@ async fn test_concurrent_allocations(ex: &Executor<'_>) {
                 let layout = Layout::from_size_align(64, 8).unwrap();
                 let block = allocator.allocate(layout).await.unwrap();
                 smol::future::yield_now().await;
-                allocator.deallocate(block).await;
+                allocator.deallocate(block).await.unwrap();
             }));
         }
 
@@ -63,13 +78,15 @@ async fn test_pool_reuse(ex: &Executor<'_>) {
         // First allocation
         let block1 = allocator.allocate(layout).await.unwrap();
         let gen1 = block1.generation();
-        allocator.deallocate(block1).await;
+        let addr1 = &*block1 as *const Block as usize;
+        allocator.deallocate(block1).await.unwrap();
 
-        // Second allocation should reuse block
+        // Second allocation should reuse block under a new generation
         let block2 = allocator.allocate(layout).await.unwrap();
         let gen2 = block2.generation();
-        assert_eq!(gen1, gen2, "Block should be reused");
-        allocator.deallocate(block2).await;
+        assert_eq!(addr1, &*block2 as *const Block as usize, "Block should be reused");
+        assert!(gen2 > gen1, "Reused block should get a new generation");
+        allocator.deallocate(block2).await.unwrap();
     }).await;
 }
 
@@ -85,7 +102,8 @@ async fn test_memory_limits(ex: &Executor<'_>) {
             cache_ttl: Duration::from_secs(60),
             max_caches: 100,
             initial_pool_size: 512,
-            zero_on_dealloc: true,
+            zero_policy: ZeroPolicy::OnDealloc,
+            ..AtomAllocConfig::default()
         };
 
         println!("\n=== Starting memory limits test with size classes ===");
@@ -111,7 +129,7 @@ async fn test_memory_limits(ex: &Executor<'_>) {
             "Expected OutOfMemory for too-large block"
         );
 
-        allocator.deallocate(block1).await;
+        allocator.deallocate(block1).await.unwrap();
     }).await;
 }
 
@@ -127,7 +145,8 @@ async fn test_strict_memory_limits(ex: &Executor<'_>) {
             cache_ttl: Duration::from_secs(60),
             max_caches: 100,
             initial_pool_size: 512,
-            zero_on_dealloc: true,
+            zero_policy: ZeroPolicy::OnDealloc,
+            ..AtomAllocConfig::default()
         };
 
         println!("\n=== Starting strict memory limit test ===");
@@ -150,7 +169,7 @@ async fn test_strict_memory_limits(ex: &Executor<'_>) {
         assert!(matches!(result.as_ref(), Err(AtomAllocError::OutOfMemory)));
 
         // Free first block
-        allocator.deallocate(block1).await;
+        allocator.deallocate(block1).await.unwrap();
         let stats2 = allocator.stats().await;
         println!("After deallocation: {:?}", stats2);
         assert_eq!(stats2.current, 0);
@@ -161,6 +180,1387 @@ async fn test_strict_memory_limits(ex: &Executor<'_>) {
         println!("After final allocation: {:?}", stats3);
         assert_eq!(stats3.current, 512);
 
-        allocator.deallocate(block2).await;
+        allocator.deallocate(block2).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_typed_accessors(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let allocator = AtomAlloc::new().await;
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        let block = allocator.allocate(layout).await.unwrap();
+
+        block.write_u16_le(0, 0x1234).await.unwrap();
+        assert_eq!(block.read(0, 2).await.unwrap(), vec![0x34, 0x12]);
+        assert_eq!(block.read_u16_be(0).await.unwrap(), 0x3412);
+
+        block.write_i64_be(8, -2).await.unwrap();
+        assert_eq!(block.read_i64_be(8).await.unwrap(), -2);
+        block.write_f32_le(16, 1.5).await.unwrap();
+        assert_eq!(block.read_f32_le(16).await.unwrap(), 1.5);
+
+        // Accessors share the bounds checks of read/write
+        assert!(block.write_u64_le(60, 1).await.is_err());
+
+        // Cursors advance through consecutive fields
+        let mut writer = block.writer();
+        writer.write_u8(7).await.unwrap();
+        writer.write_u32_be(0xdead_beef).await.unwrap();
+        writer.write_bytes(b"abc").await.unwrap();
+        assert_eq!(writer.position(), 8);
+
+        let mut reader = block.reader();
+        assert_eq!(reader.read_u8().await.unwrap(), 7);
+        assert_eq!(reader.read_u32_be().await.unwrap(), 0xdead_beef);
+        assert_eq!(reader.read_bytes(3).await.unwrap(), b"abc");
+        assert_eq!(reader.remaining(), 56);
+        assert!(reader.skip(57).is_err());
+        assert_eq!(reader.position(), 8);
+
+        allocator.deallocate(block).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_block_slices(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let allocator = AtomAlloc::new().await;
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        let block = allocator.allocate(layout).await.unwrap();
+
+        // Header in the first 8 bytes, payload after it
+        let (header, payload) = block.slice(..).unwrap().split_at(8).unwrap();
+        assert_eq!(header.len(), 8);
+        assert_eq!(payload.len(), 56);
+        assert_eq!(payload.offset(), 8);
+
+        payload.write(0, &[1, 2, 3]).await.unwrap();
+        assert_eq!(block.read(8, 3).await.unwrap(), vec![1, 2, 3]);
+        header.write_u32_be(4, 7).await.unwrap();
+        assert_eq!(block.read_u32_be(4).await.unwrap(), 7);
+
+        // Slices enforce their own bounds
+        assert!(matches!(
+            header.write(6, &[0; 4]).await,
+            Err(BlockError::OutOfBounds { size: 8, .. })
+        ));
+        assert!(block.slice(60..70).is_err());
+        assert!(matches!(
+            block.slice(..=usize::MAX),
+            Err(BlockError::OutOfBounds { .. })
+        ));
+        assert!(matches!(
+            block.fill((Bound::Excluded(usize::MAX), Bound::Unbounded), 0).await,
+            Err(BlockError::OutOfBounds { .. })
+        ));
+
+        let inner = payload.slice(1..=2).unwrap();
+        assert_eq!(inner.read(0, 2).await.unwrap(), vec![2, 3]);
+
+        // Slices keep the parent alive but not allocated
+        drop(header);
+        drop(payload);
+        allocator.deallocate(block).await.unwrap();
+        assert!(matches!(
+            inner.read(0, 2).await,
+            Err(BlockError::NotInitialized)
+        ));
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_consistent_reads(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let allocator = AtomAlloc::new().await;
+        let layout = Layout::from_size_align(4096, 8).unwrap();
+        let block = allocator.allocate(layout).await.unwrap();
+
+        // Writes span several chunks, so they yield part-way through
+        let writer = {
+            let block = block.clone();
+            smol::spawn(async move {
+                for i in 0..50u8 {
+                    block.write(0, &[i; 4096]).await.unwrap();
+                }
+            })
+        };
+
+        let mut readers = vec![];
+        for _ in 0..4 {
+            let block = block.clone();
+            readers.push(smol::spawn(async move {
+                for _ in 0..50 {
+                    let data = block.read_consistent(0, 4096).await.unwrap();
+                    assert!(data.iter().all(|&b| b == data[0]), "torn read");
+                }
+            }));
+        }
+
+        writer.await;
+        for reader in readers {
+            reader.await;
+        }
+        assert_eq!(block.version(), 100);
+
+        allocator.deallocate(block).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_optimistic_writes(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let allocator = AtomAlloc::new().await;
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        let block = allocator.allocate(layout).await.unwrap();
+
+        // A stale version is rejected
+        let (_, version) = block.read_versioned(0, 8).await.unwrap();
+        block.write_u64_le(0, 1).await.unwrap();
+        assert!(matches!(
+            block.write_if_version(version, 0, &[0; 8]).await,
+            Err(BlockError::VersionConflict { current }) if current == version + 2
+        ));
+
+        // Concurrent read-modify-write increments without a mutex
+        let mut handles = vec![];
+        for _ in 0..8 {
+            let block = block.clone();
+            handles.push(smol::spawn(async move {
+                for _ in 0..25 {
+                    loop {
+                        let (bytes, version) = block.read_versioned(0, 8).await.unwrap();
+                        let counter = u64::from_le_bytes(bytes.try_into().unwrap());
+                        let next = (counter + 1).to_le_bytes();
+                        match block.write_if_version(version, 0, &next).await {
+                            Ok(()) => break,
+                            Err(BlockError::VersionConflict { .. }) => continue,
+                            Err(e) => panic!("unexpected error: {}", e),
+                        }
+                    }
+                }
+            }));
+        }
+        for handle in handles {
+            handle.await;
+        }
+        assert_eq!(block.read_u64_le(0).await.unwrap(), 201);
+
+        allocator.deallocate(block).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_block_leases(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let allocator = AtomAlloc::new().await;
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        let block = allocator.allocate(layout).await.unwrap();
+        let generation = block.generation();
+
+        let write = block.lease_write().await;
+        assert!(block.try_lease_read().is_none());
+        assert!(block.try_lease_write().is_none());
+        write.write_u32_le(0, 42).await.unwrap();
+        drop(write);
+
+        // Any number of readers, but no writer while they hold leases
+        let read1 = block.lease_read().await;
+        let read2 = block.try_lease_read().unwrap();
+        assert!(block.try_lease_write().is_none());
+        assert_eq!(read1.read_u32_le(0).await.unwrap(), 42);
+        assert_eq!(read2.read_u32_le(0).await.unwrap(), 42);
+        drop(read1);
+        assert!(block.try_lease_write().is_none());
+        drop(read2);
+
+        // Lease bits do not leak into the generation
+        assert_eq!(block.generation(), generation);
+
+        // Writers queued behind a reader get in once it is released
+        let read = block.lease_read().await;
+        let pending = {
+            let block = block.clone();
+            smol::spawn(async move {
+                let lease = block.lease_write().await;
+                lease.write_u32_le(0, 7).await.unwrap();
+            })
+        };
+        smol::future::yield_now().await;
+        assert_eq!(read.read_u32_le(0).await.unwrap(), 42);
+        drop(read);
+        pending.await;
+        assert_eq!(block.read_u32_le(0).await.unwrap(), 7);
+
+        // Plain writes wait for read leases too
+        let read = block.lease_read().await;
+        let mut write = Box::pin(block.write_u32_le(0, 9));
+        assert!(smol::future::poll_once(&mut write).await.is_none());
+        assert!(smol::future::poll_once(&mut write).await.is_none());
+        assert_eq!(read.read_u32_le(0).await.unwrap(), 7);
+        drop(read);
+        write.await.unwrap();
+        assert_eq!(block.read_u32_le(0).await.unwrap(), 9);
+
+        // And so does clearing, through the block or through `BlockOps`
+        let read = block.lease_read().await;
+        let mut clear = Box::pin(block.clear());
+        assert!(smol::future::poll_once(&mut clear).await.is_none());
+        let arc = Pin::into_inner(block.clone());
+        let mut ops_clear = Box::pin(BlockOps::clear(Pin::new(&arc)));
+        assert!(smol::future::poll_once(&mut ops_clear).await.is_none());
+        assert_eq!(read.read_u32_le(0).await.unwrap(), 9);
+        drop(read);
+        clear.await;
+        ops_clear.await;
+        drop(arc);
+        assert_eq!(block.read_u32_le(0).await.unwrap(), 0);
+
+        // A lease holder fills and clears through its lease
+        let write = block.lease_write().await;
+        write.fill(..4, 3).await.unwrap();
+        assert_eq!(write.read(0, 5).await.unwrap(), vec![3, 3, 3, 3, 0]);
+        write.clear().await;
+        assert_eq!(write.read_u32_le(0).await.unwrap(), 0);
+        drop(write);
+
+        allocator.deallocate(block).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_frozen_blocks(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let allocator = AtomAlloc::new().await;
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        let block = allocator.allocate(layout).await.unwrap();
+        block.write(0, b"shared").await.unwrap();
+
+        // Nothing else may be able to write to a frozen block
+        let clone = block.clone();
+        let block = block.freeze().err().unwrap();
+        let handle = block.handle();
+        let block = block.freeze().err().unwrap();
+        drop((clone, handle));
+        let weak = Arc::downgrade(&Pin::into_inner(block.clone()));
+        let block = block.freeze().err().unwrap();
+        drop(weak);
+
+        let frozen = block.freeze().ok().unwrap();
+        let mut readers = vec![];
+        for _ in 0..4 {
+            let frozen = frozen.clone();
+            readers.push(smol::spawn(async move {
+                assert_eq!(frozen.read(0, 6).await.unwrap(), b"shared");
+            }));
+        }
+        for reader in readers {
+            reader.await;
+        }
+
+        // Only the last clone returns the block
+        let freed_before = allocator.stats().await.freed;
+        let last = frozen.clone();
+        drop(frozen);
+        assert_eq!(allocator.stats().await.freed, freed_before);
+        drop(last);
+        // The return runs as its own task
+        while allocator.stats().await.freed == freed_before {
+            smol::Timer::after(Duration::from_millis(1)).await;
+        }
+        assert_eq!(allocator.stats().await.freed, freed_before + 64);
+
+        // And it is handed out again from the cache, zeroed
+        let reused = allocator.allocate(layout).await.unwrap();
+        assert_eq!(reused.read(0, 6).await.unwrap(), vec![0; 6]);
+        allocator.deallocate(reused).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_cow_clones(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let allocator = AtomAlloc::new().await;
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        let template = allocator.allocate(layout).await.unwrap();
+        template.write(0, b"template").await.unwrap();
+
+        let mut copy = template.cow_clone();
+        let allocated_before = allocator.stats().await.allocated;
+        assert!(copy.is_shared());
+        assert_eq!(copy.read(0, 8).await.unwrap(), b"template");
+        assert_eq!(allocator.stats().await.allocated, allocated_before);
+
+        // The first write allocates and copies; the template is untouched
+        copy.write(0, b"T").await.unwrap();
+        assert!(!copy.is_shared());
+        assert_eq!(copy.read(0, 8).await.unwrap(), b"Template");
+        assert_eq!(template.read(0, 8).await.unwrap(), b"template");
+
+        let stats = allocator.stats().await;
+        assert_eq!(stats.cow_copies, 1);
+        assert_eq!(stats.allocated, allocated_before + 64);
+
+        // Dropping the handle returns the private copy
+        drop(copy);
+        while allocator.stats().await.current > 64 {
+            smol::Timer::after(Duration::from_millis(1)).await;
+        }
+        assert_eq!(allocator.stats().await.current, 64);
+
+        allocator.deallocate(template).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_cow_of_freed_block(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let config = AtomAllocConfig {
+            quarantine_blocks: 4,
+            track_allocations: true,
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = AtomAlloc::with_config(config).await;
+        let layout = Layout::from_size_align(64, 8).unwrap();
+
+        // A freed template can no longer be copied, and the failed copy
+        // leaves nothing allocated behind
+        let template = allocator.allocate(layout).await.unwrap();
+        let mut copy = template.cow_clone();
+        allocator.deallocate(template).await.unwrap();
+        assert!(copy.write(0, b"x").await.is_err());
+        drop(copy);
+        assert_eq!(allocator.stats().await.current, 0);
+        assert!(allocator.live_allocations().is_empty());
+
+        // Nor can one that has been handed out again
+        let allocator = AtomAlloc::new().await;
+        let template = allocator.allocate(layout).await.unwrap();
+        let mut copy = template.cow_clone();
+        allocator.deallocate(template).await.unwrap();
+        assert!(matches!(
+            copy.read(0, 8).await,
+            Err(BlockError::NotInitialized)
+        ));
+        let reused = allocator.allocate(layout).await.unwrap();
+        reused.write(0, b"new owner").await.unwrap();
+        assert!(matches!(
+            copy.read(0, 8).await,
+            Err(BlockError::InvalidGeneration { .. })
+        ));
+        assert!(copy.read_u64_le(0).await.is_err());
+        assert!(matches!(
+            copy.write(0, b"x").await,
+            Err(AtomAllocError::BlockError(BlockError::InvalidGeneration { .. }))
+        ));
+        assert_eq!(reused.read(0, 9).await.unwrap(), b"new owner");
+        drop(copy);
+        allocator.deallocate(reused).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_bulk_operations(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let allocator = AtomAlloc::new().await;
+        let layout = Layout::from_size_align(2048, 8).unwrap();
+        let a = allocator.allocate(layout).await.unwrap();
+        let b = allocator.allocate(layout).await.unwrap();
+
+        a.fill(.., 0xff).await.unwrap();
+        a.fill(4..8, 0).await.unwrap();
+        assert_eq!(a.read(2, 8).await.unwrap(), vec![0xff, 0xff, 0, 0, 0, 0, 0xff, 0xff]);
+        assert!(a.fill(2000..3000, 1).await.is_err());
+
+        b.fill(.., 0xff).await.unwrap();
+        assert!(!a.eq_range(0, &b, 0, 2048).await.unwrap());
+        assert!(a.eq_range(8, &b, 0, 2040).await.unwrap());
+        assert!(a.eq_range(0, &b, 1, 2048).await.is_err());
+
+        // Patterns past the first chunk boundary are found too
+        a.write(1500, b"needle").await.unwrap();
+        assert_eq!(a.find(b"needle").await.unwrap(), Some(1500));
+        assert_eq!(a.find(b"missing").await.unwrap(), None);
+        assert_eq!(a.find(&[0, 0, 0, 0]).await.unwrap(), Some(4));
+
+        assert!(a.starts_with(&[0xff, 0xff, 0xff, 0xff, 0]).await.unwrap());
+        assert!(!a.starts_with(&[0]).await.unwrap());
+
+        allocator.deallocate(a).await.unwrap();
+        allocator.deallocate(b).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_block_checksums(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let config = AtomAllocConfig {
+            verify_checksums_on_read: true,
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = AtomAlloc::with_config(config).await;
+        let layout = Layout::from_size_align(256, 8).unwrap();
+        let block = allocator.allocate(layout).await.unwrap();
+        block.write(0, b"long-lived").await.unwrap();
+
+        assert!(!block.is_sealed());
+        let checksum = block.seal().await;
+        assert!(block.is_sealed());
+        assert_eq!(block.seal().await, checksum);
+        block.verify().await.unwrap();
+        assert_eq!(block.read(0, 10).await.unwrap(), b"long-lived");
+
+        // Writes invalidate the seal until the block is sealed again
+        block.write(0, b"L").await.unwrap();
+        assert!(!block.is_sealed());
+        block.verify().await.unwrap();
+        assert_ne!(block.seal().await, checksum);
+
+        // Scrubbing a freed block keeps the seal, so a reference kept past
+        // deallocate sees that the contents changed under it
+        for poison in [false, true] {
+            let config = AtomAllocConfig {
+                zero_policy: ZeroPolicy::OnDealloc,
+                poison_freed_blocks: poison,
+                ..AtomAllocConfig::get_default_for_tests()
+            };
+            let allocator = AtomAlloc::with_config(config).await;
+            let block = allocator.allocate(layout).await.unwrap();
+            block.write(0, b"long-lived").await.unwrap();
+            let checksum = block.seal().await;
+            let stale = block.clone();
+            allocator.deallocate(block).await.unwrap();
+            assert!(matches!(
+                stale.verify().await,
+                Err(BlockError::ChecksumMismatch { expected, .. }) if expected == checksum
+            ));
+
+            // The next owner starts out unsealed
+            let block = allocator.allocate(layout).await.unwrap();
+            assert_eq!(&*block as *const Block, &*stale as *const Block);
+            assert!(!block.is_sealed());
+            block.verify().await.unwrap();
+            allocator.deallocate(block).await.unwrap();
+        }
+
+        allocator.deallocate(block).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_poisoned_blocks(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let config = AtomAllocConfig {
+            poison_freed_blocks: true,
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = AtomAlloc::with_config(config).await;
+        let layout = Layout::from_size_align(64, 8).unwrap();
+
+        // Untouched freed blocks come back zeroed
+        let block = allocator.allocate(layout).await.unwrap();
+        block.write(0, &[1; 64]).await.unwrap();
+        allocator.deallocate(block).await.unwrap();
+        let block = allocator.allocate(layout).await.unwrap();
+        assert_eq!(block.read(0, 64).await.unwrap(), vec![0; 64]);
+
+        // Stale clones cannot touch a released block at all
+        let stale = block.clone();
+        allocator.deallocate(block).await.unwrap();
+        assert!(matches!(
+            stale.write(10, &[7]).await,
+            Err(BlockError::NotInitialized)
+        ));
+        assert!(matches!(
+            stale.read(0, 1).await,
+            Err(BlockError::NotInitialized)
+        ));
+        assert!(matches!(
+            stale.find(&[7]).await,
+            Err(BlockError::NotInitialized)
+        ));
+        assert!(matches!(
+            stale.starts_with(&[0]).await,
+            Err(BlockError::NotInitialized)
+        ));
+        assert!(matches!(
+            stale.eq_range(0, &stale, 0, 1).await,
+            Err(BlockError::NotInitialized)
+        ));
+
+        // A write through a stale clone that grabbed the block anyway is
+        // caught on the next reuse
+        let generation = stale.generation();
+        assert!(stale.try_acquire());
+        stale.write(10, &[7]).await.unwrap();
+        stale.release();
+        assert!(matches!(
+            allocator.allocate(layout).await,
+            Err(AtomAllocError::UseAfterFree { size: 64, generation: g, offset: 10 }) if g == generation
+        ));
+
+        // The corrupted block is retired; allocation carries on
+        let block = allocator.allocate(layout).await.unwrap();
+        assert_eq!(block.read(0, 64).await.unwrap(), vec![0; 64]);
+        assert_ne!(&*block as *const Block, &*stale as *const Block);
+        allocator.deallocate(block).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_quarantine(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let config = AtomAllocConfig {
+            max_memory: 1024,
+            max_block_size: 512,
+            initial_pool_size: 512,
+            quarantine_blocks: 2,
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = AtomAlloc::with_config(config).await;
+        let layout = Layout::from_size_align(256, 8).unwrap();
+
+        let a = allocator.allocate(layout).await.unwrap();
+        let b = allocator.allocate(layout).await.unwrap();
+        let c = allocator.allocate(layout).await.unwrap();
+
+        allocator.deallocate(a).await.unwrap();
+        allocator.deallocate(b).await.unwrap();
+        assert_eq!(allocator.stats().await.quarantined, 512);
+
+        // A third block pushes the oldest one back into circulation
+        allocator.deallocate(c).await.unwrap();
+        assert_eq!(allocator.stats().await.quarantined, 512);
+        let a = allocator.allocate(layout).await.unwrap();
+        assert_eq!(allocator.stats().await.quarantined, 512);
+
+        // At the memory limit the quarantine is released instead of failing
+        let b = allocator.allocate(layout).await.unwrap();
+        assert_eq!(allocator.stats().await.quarantined, 0);
+        let c = allocator.allocate(layout).await.unwrap();
+        assert!(matches!(
+            allocator.allocate(layout).await,
+            Err(AtomAllocError::OutOfMemory)
+        ));
+
+        allocator.deallocate(a).await.unwrap();
+        allocator.deallocate(b).await.unwrap();
+        allocator.deallocate(c).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_allocation_tracking(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let config = AtomAllocConfig {
+            track_allocations: true,
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = AtomAlloc::with_config(config).await;
+        let layout = Layout::from_size_align(64, 8).unwrap();
+
+        let line = line!() + 1;
+        let a = allocator.allocate(layout).await.unwrap();
+        let b = allocator.allocate(Layout::from_size_align(100, 8).unwrap()).await.unwrap();
+
+        let mut live = allocator.live_allocations();
+        live.sort_by_key(|info| info.size);
+        assert_eq!(live.len(), 2);
+        assert_eq!(live[0].size, 64);
+        assert_eq!(live[0].generation, a.generation());
+        assert_eq!(live[0].location.file(), file!());
+        assert_eq!(live[0].location.line(), line);
+        assert_eq!(live[1].size, 128);
+
+        allocator.deallocate(a).await.unwrap();
+        assert_eq!(allocator.live_allocations().len(), 1);
+
+        // Copy-on-write copies are attributed to the clone site
+        let line = line!() + 1;
+        let mut copy = b.cow_clone();
+        copy.write(0, &[1]).await.unwrap();
+        let live = allocator.live_allocations();
+        let info = live.iter().find(|info| info.generation != b.generation()).unwrap();
+        assert_eq!(info.location.file(), file!());
+        assert_eq!(info.location.line(), line);
+        drop(copy);
+
+        // Blocks returned through a frozen handle are untracked as well
+        while allocator.live_allocations().len() > 1 {
+            smol::Timer::after(Duration::from_millis(1)).await;
+        }
+        drop(b.freeze().ok().unwrap());
+        while !allocator.live_allocations().is_empty() {
+            smol::Timer::after(Duration::from_millis(1)).await;
+        }
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_double_free_and_foreign_blocks(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let allocator = AtomAlloc::new().await;
+        let other = AtomAlloc::new().await;
+        let layout = Layout::from_size_align(64, 8).unwrap();
+
+        let block = allocator.allocate(layout).await.unwrap();
+        let stale = block.clone();
+        allocator.deallocate(block).await.unwrap();
+        assert!(matches!(
+            allocator.deallocate(stale).await,
+            Err(AtomAllocError::DoubleFree { size: 64, .. })
+        ));
+
+        // The rejected free did not queue the block a second time
+        let a = allocator.allocate(layout).await.unwrap();
+        let b = allocator.allocate(layout).await.unwrap();
+        assert!(!std::ptr::eq(&*a, &*b));
+
+        let foreign = other.allocate(layout).await.unwrap();
+        assert!(matches!(
+            allocator.deallocate(foreign.clone()).await,
+            Err(AtomAllocError::ForeignBlock { .. })
+        ));
+        other.deallocate(foreign).await.unwrap();
+
+        allocator.deallocate(a).await.unwrap();
+        allocator.deallocate(b).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_block_handles(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let allocator = AtomAlloc::new().await;
+        let layout = Layout::from_size_align(64, 8).unwrap();
+
+        let handle = allocator.allocate_handle(layout).await.unwrap();
+        handle.write_u32_le(0, 0xfeed_beef).await.unwrap();
+        assert_eq!(handle.read_u32_le(0).await.unwrap(), 0xfeed_beef);
+
+        let stale = handle.clone();
+        allocator.deallocate_handle(handle).await.unwrap();
+
+        // Reissuing the block invalidates every handle to the old generation
+        let block = allocator.allocate(layout).await.unwrap();
+        assert!(!stale.is_valid());
+        assert!(matches!(
+            stale.read(0, 4).await,
+            Err(BlockError::InvalidGeneration { .. })
+        ));
+        assert!(matches!(
+            stale.write(0, &[1]).await,
+            Err(BlockError::InvalidGeneration { .. })
+        ));
+        assert!(matches!(
+            allocator.deallocate_handle(stale).await,
+            Err(AtomAllocError::BlockError(BlockError::InvalidGeneration { .. }))
+        ));
+
+        let fresh = block.handle();
+        assert_eq!(fresh.generation(), block.generation());
+        fresh.write(0, &[7]).await.unwrap();
+
+        // The generation moves on as the block is freed, so the handle is
+        // cut off even while the block is being acquired again
+        let raw = block.clone();
+        allocator.deallocate(block).await.unwrap();
+        assert_ne!(raw.generation(), fresh.generation());
+        assert!(raw.try_acquire());
+        assert!(matches!(
+            fresh.write(0, &[8]).await,
+            Err(BlockError::InvalidGeneration { .. })
+        ));
+        raw.release();
+
+        // A handle write waiting behind another writer does not land once
+        // the block has been reissued in the meantime
+        let layout = Layout::from_size_align(2048, 8).unwrap();
+        let block = allocator.allocate(layout).await.unwrap();
+        let handle = block.handle();
+        let data = vec![1; 2048];
+        let mut writer = Box::pin(block.write(0, &data));
+        assert!(smol::future::poll_once(&mut writer).await.is_none());
+        let mut waiting = Box::pin(handle.write(0, b"stale"));
+        assert!(smol::future::poll_once(&mut waiting).await.is_none());
+        writer.await.unwrap();
+
+        allocator.deallocate(block).await.unwrap();
+        let block = allocator.allocate(layout).await.unwrap();
+        block.write(0, b"owner").await.unwrap();
+        assert!(matches!(
+            waiting.await,
+            Err(BlockError::InvalidGeneration { .. })
+        ));
+        assert_eq!(block.read(0, 5).await.unwrap(), b"owner");
+        allocator.deallocate(block).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_in_use_blocks(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let allocator = AtomAlloc::new().await;
+        let layout = Layout::from_size_align(64, 8).unwrap();
+
+        let block = allocator.allocate(layout).await.unwrap();
+        assert!(block.is_in_use());
+        let stale = block.clone();
+        allocator.deallocate(block).await.unwrap();
+        assert!(!stale.is_in_use());
+        assert!(matches!(
+            stale.fill(.., 1).await,
+            Err(BlockError::NotInitialized)
+        ));
+
+        // A cached block grabbed behind the allocator's back is reported
+        // instead of being handed out a second time
+        assert!(stale.try_acquire());
+        assert!(matches!(
+            allocator.allocate(layout).await,
+            Err(AtomAllocError::BlockError(BlockError::InUse))
+        ));
+
+        // It stays cached and is reused once its holder lets go
+        stale.release();
+        let block = allocator.allocate(layout).await.unwrap();
+        assert!(block.is_in_use());
+        assert_eq!(&*block as *const Block, &*stale as *const Block);
+        allocator.deallocate(block).await.unwrap();
+
+        // Same for a block waiting in the pool behind a full size class
+        let config = AtomAllocConfig {
+            class_capacity_blocks: 1,
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = AtomAlloc::with_config(config).await;
+        let cached = allocator.allocate(layout).await.unwrap();
+        let pooled = allocator.allocate(layout).await.unwrap();
+        let stale = pooled.clone();
+        allocator.deallocate(cached).await.unwrap();
+        allocator.deallocate(pooled).await.unwrap();
+
+        assert!(stale.try_acquire());
+        let cached = allocator.allocate(layout).await.unwrap();
+        assert!(matches!(
+            allocator.allocate(layout).await,
+            Err(AtomAllocError::BlockError(BlockError::InUse))
+        ));
+
+        stale.release();
+        let pooled = allocator.allocate(layout).await.unwrap();
+        assert_eq!(&*pooled as *const Block, &*stale as *const Block);
+        allocator.deallocate(cached).await.unwrap();
+        allocator.deallocate(pooled).await.unwrap();
+
+        // A held block at the end FIFO pops from does not hide the ones
+        // behind it
+        let config = AtomAllocConfig {
+            cache_policy: CachePolicyKind::Fifo,
+            max_caches: 1,
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = AtomAlloc::with_config(config).await;
+        let oldest = allocator.allocate(layout).await.unwrap();
+        let newest = allocator.allocate(layout).await.unwrap();
+        let (stale, newest_addr) = (oldest.clone(), &*newest as *const Block as usize);
+        allocator.deallocate(oldest).await.unwrap();
+        allocator.deallocate(newest).await.unwrap();
+
+        assert!(stale.try_acquire());
+        let block = allocator.allocate(layout).await.unwrap();
+        assert_eq!(&*block as *const Block as usize, newest_addr);
+        stale.release();
+        let other = allocator.allocate(layout).await.unwrap();
+        assert_eq!(&*other as *const Block, &*stale as *const Block);
+        allocator.deallocate(block).await.unwrap();
+        allocator.deallocate(other).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_zero_policies(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        for (policy, zeroed) in [
+            (ZeroPolicy::OnDealloc, true),
+            (ZeroPolicy::OnAlloc, true),
+            (ZeroPolicy::Lazy, true),
+            (ZeroPolicy::Background, true),
+            (ZeroPolicy::Never, false),
+        ] {
+            let config = AtomAllocConfig {
+                zero_policy: policy,
+                ..AtomAllocConfig::get_default_for_tests()
+            };
+            let allocator = AtomAlloc::with_config(config).await;
+
+            let block = allocator.allocate(layout).await.unwrap();
+            let addr = &*block as *const Block as usize;
+            block.write(0, &[9; 64]).await.unwrap();
+            allocator.deallocate(block).await.unwrap();
+
+            // Background zeroing finishes on its own time
+            while allocator.stats().await.zeroing > 0 {
+                smol::Timer::after(Duration::from_millis(1)).await;
+            }
+
+            let block = allocator.allocate(layout).await.unwrap();
+            assert_eq!(addr, &*block as *const Block as usize, "{:?}", policy);
+            let expected = if zeroed { 0 } else { 9 };
+            assert_eq!(block.read(0, 64).await.unwrap(), vec![expected; 64], "{:?}", policy);
+            allocator.deallocate(block).await.unwrap();
+        }
+    }).await;
+}
+
+#[derive(Debug)]
+struct NoCaching;
+
+impl CachePolicy for NoCaching {
+    fn on_get(&self, _view: &CacheView) -> CacheEnd {
+        CacheEnd::Newest
+    }
+
+    fn on_return(&self, _view: &CacheView) -> bool {
+        false
+    }
+
+    fn should_evict(&self, _view: &CacheView, _idle: Duration) -> bool {
+        false
+    }
+}
+
+#[apply(test!)]
+async fn test_cache_policies(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        let addr = |block: &Block| block as *const Block as usize;
+
+        // LIFO hands back the most recently freed block, FIFO the oldest
+        for (policy, expect_last) in [(CachePolicyKind::Lifo, true), (CachePolicyKind::Fifo, false)] {
+            // A single shard keeps the order independent of thread hops
+            let config = AtomAllocConfig {
+                cache_policy: policy,
+                max_caches: 1,
+                ..AtomAllocConfig::get_default_for_tests()
+            };
+            let allocator = AtomAlloc::with_config(config).await;
+            let first = allocator.allocate(layout).await.unwrap();
+            let last = allocator.allocate(layout).await.unwrap();
+            let (first_addr, last_addr) = (addr(&first), addr(&last));
+            allocator.deallocate(first).await.unwrap();
+            allocator.deallocate(last).await.unwrap();
+
+            let block = allocator.allocate(layout).await.unwrap();
+            let expected = if expect_last { last_addr } else { first_addr };
+            assert_eq!(addr(&block), expected);
+            allocator.deallocate(block).await.unwrap();
+        }
+
+        // LRU gives blocks idle past the TTL back to the pool
+        let config = AtomAllocConfig {
+            cache_policy: CachePolicyKind::Lru,
+            cache_ttl: Duration::from_millis(5),
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = AtomAlloc::with_config(config).await;
+        let held = allocator.allocate(layout).await.unwrap();
+        let old = allocator.allocate(layout).await.unwrap();
+        let recent = allocator.allocate(layout).await.unwrap();
+        let stale = held.clone();
+        allocator.deallocate(held).await.unwrap();
+        allocator.deallocate(old).await.unwrap();
+        // A block someone holds is skipped, not in the way
+        assert!(stale.try_acquire());
+        smol::Timer::after(Duration::from_millis(20)).await;
+        allocator.deallocate(recent).await.unwrap();
+        assert_eq!(allocator.stats().await.evictions, 1);
+        stale.release();
+
+        // Custom policies plug in through the config; declined blocks go
+        // back to the pool, which hands them out again
+        let config = AtomAllocConfig {
+            cache_policy: CachePolicyKind::Custom(Arc::new(NoCaching)),
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = AtomAlloc::with_config(config).await;
+        let block = allocator.allocate(layout).await.unwrap();
+        let block_addr = addr(&block);
+        allocator.deallocate(block).await.unwrap();
+        let block = allocator.allocate(layout).await.unwrap();
+        assert_eq!(addr(&block), block_addr);
+        assert_eq!(allocator.stats().await.evictions, 0);
+        allocator.deallocate(block).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_class_capacity(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let config = AtomAllocConfig {
+            max_memory: 2048,
+            max_block_size: 1024,
+            initial_pool_size: 512,
+            class_capacity_blocks: 1,
+            class_capacity_bytes: 512, // no room for any 1024B block
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = AtomAlloc::with_config(config).await;
+        let small = Layout::from_size_align(512, 8).unwrap();
+        let large = Layout::from_size_align(1024, 8).unwrap();
+
+        let blocks = [
+            allocator.allocate(small).await.unwrap(),
+            allocator.allocate(small).await.unwrap(),
+            allocator.allocate(small).await.unwrap(),
+        ];
+        assert!(allocator.allocate(large).await.is_err());
+        let weak = blocks
+            .each_ref()
+            .map(|block| Arc::downgrade(&Pin::into_inner(block.clone())));
+
+        for block in blocks {
+            allocator.deallocate(block).await.unwrap();
+        }
+        assert_eq!(allocator.stats().await.current, 0);
+
+        // Only one block stays cached; the rest no longer count against
+        // the memory limit
+        let block = allocator.allocate(large).await.unwrap();
+        allocator.deallocate(block).await.unwrap();
+
+        // Released blocks are kept for reuse only while they fit next to
+        // the budget, so one was dropped to make room for the large block
+        assert_eq!(weak.iter().filter(|block| block.strong_count() > 0).count(), 2);
+
+        // The rest are still reused, under the budget
+        let blocks = [
+            allocator.allocate(small).await.unwrap(),
+            allocator.allocate(small).await.unwrap(),
+        ];
+        assert_eq!(allocator.stats().await.current, 1024);
+        for block in blocks {
+            allocator.deallocate(block).await.unwrap();
+        }
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_class_fallback(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let config = AtomAllocConfig {
+            max_memory: 2048,
+            max_block_size: 512,
+            initial_pool_size: 512,
+            class_fallback: 1,
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = AtomAlloc::with_config(config).await;
+        let small = Layout::from_size_align(256, 8).unwrap();
+        let large = Layout::from_size_align(512, 8).unwrap();
+
+        // Fill the budget with 512B blocks and free them
+        let blocks = [
+            allocator.allocate(large).await.unwrap(),
+            allocator.allocate(large).await.unwrap(),
+            allocator.allocate(large).await.unwrap(),
+        ];
+        for block in blocks {
+            allocator.deallocate(block).await.unwrap();
+        }
+
+        // The pool is out of room, but a cached 512B block can serve 256B
+        let block = allocator.allocate(small).await.unwrap();
+        assert_eq!(block.size(), 512);
+        let stats = allocator.stats().await;
+        assert_eq!(stats.fallbacks, 1);
+        assert_eq!(stats.fallback_waste, 256);
+        allocator.deallocate(block).await.unwrap();
+
+        let config = AtomAllocConfig {
+            class_fallback: 3,
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        assert!(config.validate().is_err());
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_sharded_caches(ex: &Executor<'_>) {
+    ex.spawn(async {
+        // A fixed shard count, however many CPUs the host has
+        let config = AtomAllocConfig {
+            cache_shards: 16,
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = Arc::new(AtomAlloc::with_config(config).await);
+        let layout = Layout::from_size_align(128, 8).unwrap();
+        let addr = |block: &Block| block as *const Block as usize;
+
+        let mut blocks = Vec::new();
+        for _ in 0..4 {
+            blocks.push(allocator.allocate(layout).await.unwrap());
+        }
+        let mut freed: Vec<_> = blocks.iter().map(|block| addr(block)).collect();
+
+        // Free each block from its own thread, landing in different shards
+        let threads: Vec<_> = blocks
+            .into_iter()
+            .map(|block| {
+                let allocator = allocator.clone();
+                std::thread::spawn(move || {
+                    smol::block_on(allocator.deallocate(block)).unwrap();
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // This thread steals all of them back before touching the pool
+        let mut blocks = Vec::new();
+        for _ in 0..4 {
+            blocks.push(allocator.allocate(layout).await.unwrap());
+        }
+        let mut reused: Vec<_> = blocks.iter().map(|block| addr(block)).collect();
+        freed.sort();
+        reused.sort();
+        assert_eq!(freed, reused);
+        assert!(allocator.stats().await.steals > 0);
+
+        for block in blocks {
+            allocator.deallocate(block).await.unwrap();
+        }
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_task_magazines(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let config = AtomAllocConfig {
+            max_memory: 4096,
+            max_block_size: 1024,
+            initial_pool_size: 1024,
+            magazine_size: 2,
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = AtomAlloc::with_config(config).await;
+        let layout = Layout::from_size_align(256, 8).unwrap();
+        let addr = |block: &Block| block as *const Block as usize;
+
+        let mut cache = allocator.task_cache();
+        let block = cache.allocate(layout).await.unwrap();
+        let first = addr(&block);
+        let handle = block.handle();
+        block.write(0, &[5; 16]).await.unwrap();
+        cache.deallocate(block).await.unwrap();
+        // Magazine stats are published in batches
+        assert_eq!(allocator.stats().await.current, 256);
+        cache.flush_stats();
+        assert_eq!(allocator.stats().await.current, 0);
+
+        // The task gets its own block straight back, under a new generation
+        let block = cache.allocate(layout).await.unwrap();
+        assert_eq!(addr(&block), first);
+        cache.flush_stats();
+        assert_eq!(allocator.stats().await.current, 256);
+        assert!(!handle.is_valid());
+        assert_eq!(block.read(0, 16).await.unwrap(), vec![0; 16]);
+
+        // Frees still go through the usual checks
+        let stale = block.clone();
+        cache.deallocate(block).await.unwrap();
+        assert!(matches!(
+            cache.deallocate(stale).await,
+            Err(AtomAllocError::DoubleFree { .. })
+        ));
+
+        // Overflowing both magazines sends one to the depot, which another
+        // task can pick up
+        let mut blocks = Vec::new();
+        for _ in 0..5 {
+            blocks.push(cache.allocate(layout).await.unwrap());
+        }
+        for block in blocks {
+            cache.deallocate(block).await.unwrap();
+        }
+        drop(cache);
+
+        let mut other = allocator.task_cache();
+        let block = other.allocate(layout).await.unwrap();
+        other.deallocate(block).await.unwrap();
+        drop(other);
+
+        // Under memory pressure the depot gives its blocks back to the pool
+        let large = Layout::from_size_align(1024, 8).unwrap();
+        let mut blocks = Vec::new();
+        for _ in 0..3 {
+            blocks.push(allocator.allocate(large).await.unwrap());
+        }
+        for block in blocks {
+            allocator.deallocate(block).await.unwrap();
+        }
+
+        // Frees wait out a configured quarantine instead
+        let config = AtomAllocConfig {
+            quarantine_blocks: 1,
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = AtomAlloc::with_config(config).await;
+        let mut cache = allocator.task_cache();
+        let block = cache.allocate(layout).await.unwrap();
+        let first = addr(&block);
+        cache.deallocate(block).await.unwrap();
+        assert_eq!(allocator.stats().await.quarantined, 256);
+        let block = cache.allocate(layout).await.unwrap();
+        assert_ne!(addr(&block), first);
+        cache.deallocate(block).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_no_lost_blocks(ex: &Executor<'_>) {
+    ex.spawn(async {
+        const TASKS: usize = 8;
+        let layout = Layout::from_size_align(256, 8).unwrap();
+
+        // Through the shared cache under both ends, and through task
+        // magazines
+        for (magazines, policy) in [
+            (false, CachePolicyKind::Lifo),
+            (false, CachePolicyKind::Fifo),
+            (true, CachePolicyKind::Lifo),
+        ] {
+            let config = AtomAllocConfig {
+                cache_policy: policy,
+                // Budget for exactly TASKS blocks of 256B
+                max_memory: (TASKS * 256 * 4).div_ceil(3),
+                max_block_size: 256,
+                initial_pool_size: 256,
+                ..AtomAllocConfig::get_default_for_tests()
+            };
+            let allocator = Arc::new(AtomAlloc::with_config(config).await);
+
+            // Tasks keep hold of the block they just freed through a stale
+            // clone while allocating the next one, so allocations keep
+            // running into cached blocks that are in use
+            let rejected = Arc::new(AtomicUsize::new(0));
+            let mut handles = vec![];
+            for task in 0..TASKS {
+                let allocator = Arc::clone(&allocator);
+                let rejected = Arc::clone(&rejected);
+                handles.push(smol::spawn(async move {
+                    let mut cache = magazines.then(|| allocator.task_cache());
+                    let mut held: Option<Pin<Arc<Block>>> = None;
+                    for round in 0..50 {
+                        let mut retries = 0;
+                        let block = loop {
+                            // A lost block would leave the budget short for good
+                            retries += 1;
+                            assert!(retries < 10_000, "blocks were lost");
+                            let result = match &mut cache {
+                                Some(cache) => cache.allocate(layout).await,
+                                None => allocator.allocate(layout).await,
+                            };
+                            match result {
+                                Ok(block) => break block,
+                                // The rest of the budget may be parked in
+                                // other tasks' magazines
+                                Err(
+                                    e @ (AtomAllocError::BlockError(BlockError::InUse)
+                                    | AtomAllocError::OutOfMemory),
+                                ) => {
+                                    if !matches!(e, AtomAllocError::OutOfMemory) {
+                                        rejected.fetch_add(1, Ordering::Relaxed);
+                                    }
+                                    assert!(magazines || !matches!(e, AtomAllocError::OutOfMemory));
+                                    if let Some(stale) = held.take() {
+                                        stale.release();
+                                    }
+                                    smol::future::yield_now().await;
+                                }
+                                Err(e) => panic!("allocation failed: {e}"),
+                            }
+                        };
+                        if let Some(stale) = held.take() {
+                            stale.release();
+                        }
+                        block.write_u16_le(0, (task * 100 + round) as u16).await.unwrap();
+                        smol::future::yield_now().await;
+
+                        let stale = block.clone();
+                        match &mut cache {
+                            Some(cache) => cache.deallocate(block).await.unwrap(),
+                            None => allocator.deallocate(block).await.unwrap(),
+                        }
+                        if stale.try_acquire() {
+                            held = Some(stale);
+                        }
+                    }
+                    if let Some(stale) = held {
+                        stale.release();
+                    }
+                }));
+            }
+            for handle in handles {
+                handle.await;
+            }
+            if !magazines {
+                assert!(rejected.load(Ordering::Relaxed) > 0);
+            }
+
+            // Every block that was ever created is still reachable; a lost
+            // one would keep its share of the budget and fail one of these
+            let mut blocks = vec![];
+            for _ in 0..TASKS {
+                blocks.push(allocator.allocate(layout).await.unwrap());
+            }
+            assert_eq!(allocator.stats().await.current, TASKS * 256);
+            for block in blocks {
+                allocator.deallocate(block).await.unwrap();
+            }
+            assert_eq!(allocator.stats().await.current, 0);
+        }
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_contended_reservations(ex: &Executor<'_>) {
+    ex.spawn(async {
+        const THREADS: usize = 16;
+        let layout = Layout::from_size_align(512, 8).unwrap();
+
+        // Racing reservations never fail while there is room
+        let allocator = Arc::new(AtomAlloc::new().await);
+        let threads: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let allocator = allocator.clone();
+                std::thread::spawn(move || {
+                    smol::block_on(async {
+                        for _ in 0..50 {
+                            let block = allocator.allocate(layout).await.unwrap();
+                            allocator.deallocate(block).await.unwrap();
+                        }
+                    })
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // Nor do they overshoot the budget: racing threads fill it exactly
+        let config = AtomAllocConfig {
+            max_memory: 64 * 1024,
+            initial_pool_size: 512,
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = Arc::new(AtomAlloc::with_config(config).await);
+        let threads: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let allocator = allocator.clone();
+                std::thread::spawn(move || {
+                    smol::block_on(async {
+                        let mut blocks = vec![];
+                        while let Ok(block) = allocator.allocate(layout).await {
+                            blocks.push(block);
+                        }
+                        blocks
+                    })
+                })
+            })
+            .collect();
+        let mut blocks = vec![];
+        for thread in threads {
+            blocks.extend(thread.join().unwrap());
+        }
+        assert_eq!(blocks.len(), 64 * 1024 * 3 / 4 / 512);
+        for block in blocks {
+            allocator.deallocate(block).await.unwrap();
+        }
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_epochs(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let allocator = AtomAlloc::new().await;
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        assert_eq!(allocator.epoch(), 0);
+
+        let block = allocator.allocate(layout).await.unwrap();
+        let old = block.handle();
+        assert_eq!(old.epoch(), Some(0));
+        assert!(old.is_valid());
+
+        // One step invalidates every earlier handle, even to live blocks
+        assert_eq!(allocator.advance_epoch().await, 1);
+        assert_eq!(allocator.epoch(), 1);
+        assert!(matches!(
+            old.read(0, 1).await,
+            Err(BlockError::StaleEpoch { epoch: 0, current: 1 })
+        ));
+        assert!(matches!(
+            block.handle().write(0, &[1]).await,
+            Err(BlockError::StaleEpoch { .. })
+        ));
+        allocator.deallocate(block).await.unwrap();
+
+        // Blocks handed out afterwards belong to the new epoch
+        let handle = allocator.allocate_handle(layout).await.unwrap();
+        assert_eq!(handle.epoch(), Some(1));
+        handle.write(0, &[1]).await.unwrap();
+        allocator.deallocate_handle(handle).await.unwrap();
+    }).await;
+}
+
+#[apply(test!)]
+async fn test_reset(ex: &Executor<'_>) {
+    ex.spawn(async {
+        let config = AtomAllocConfig {
+            max_memory: 4096,
+            max_block_size: 1024,
+            initial_pool_size: 1024,
+            ..AtomAllocConfig::get_default_for_tests()
+        };
+        let allocator = AtomAlloc::with_config(config).await;
+        let layout = Layout::from_size_align(1024, 8).unwrap();
+
+        // One block cached, one still handed out
+        let cached = allocator.allocate(layout).await.unwrap();
+        let live = allocator.allocate(layout).await.unwrap();
+        let handle = live.handle();
+        allocator.deallocate(cached).await.unwrap();
+
+        allocator.reset().await;
+
+        let stats = allocator.stats().await;
+        assert_eq!(stats.allocated, 0);
+        assert_eq!(stats.freed, 0);
+        assert_eq!(stats.current, 0);
+        assert_eq!(stats.cache_hits, 0);
+
+        // The outstanding block is cut loose rather than freed into the
+        // fresh state
+        assert!(matches!(
+            handle.read(0, 1).await,
+            Err(BlockError::StaleEpoch { .. })
+        ));
+        assert!(matches!(
+            allocator.deallocate(live).await,
+            Err(AtomAllocError::ForeignBlock { .. })
+        ));
+
+        // The whole budget is available again
+        let mut blocks = Vec::new();
+        for _ in 0..3 {
+            blocks.push(allocator.allocate(layout).await.unwrap());
+        }
+        for block in blocks {
+            allocator.deallocate(block).await.unwrap();
+        }
+        assert_eq!(allocator.stats().await.current, 0);
+        assert!(allocator.live_allocations().is_empty());
     }).await;
 }